@@ -9,7 +9,7 @@ pub fn fuel_required(mass: u32) -> i32 {
 }
 
 pub fn fuel_mass(fuel: u32) -> u32 {
-    let m = fuel_required(fuel.try_into().unwrap());
+    let m = fuel_required(fuel);
     if m <= 0 {
         return 0;
     }
@@ -1,18 +1,33 @@
+use crate::intcode::program::Program;
+use crate::intcode::Budget;
+
 pub fn entry_a(line: String) -> String {
-    let mut v = to_instruction_vect(line);
-    *v.get_mut(1).unwrap() = 12;
-    *v.get_mut(2).unwrap() = 2;
-    run_ops(&mut v).unwrap();
-    v.get(0).unwrap().to_string()
+    let program = Program::parse(&line).unwrap();
+    let mut machine = program.machine().unwrap();
+    // A plain puzzle input doesn't say how to restore the 1202 program alarm state
+    if program.patches.is_empty() {
+        machine.patch_all(&[(1, 12), (2, 2)]).unwrap();
+    }
+    machine.run_to_halt(&[]).unwrap();
+    machine.get(0).to_string()
 }
 
 pub fn entry_b(line: String) -> String {
+    let program = Program::parse(&line).unwrap();
+    let noun_addr = program.address("noun").unwrap_or(1);
+    let verb_addr = program.address("verb").unwrap_or(2);
     for noun in 0..100 {
         for verb in 0..100 {
-            let mut v = to_instruction_vect(line.clone());
-            *v.get_mut(1).unwrap() = noun;
-            *v.get_mut(2).unwrap() = verb;
-            if run_ops(&mut v).is_ok() && *v.get(0).unwrap() == 19690720 {
+            let mut machine = program.machine().unwrap();
+            machine
+                .patch_all(&[(noun_addr, noun), (verb_addr, verb)])
+                .unwrap();
+            // Some candidates jump around forever, skip them
+            machine.set_budget(Budget {
+                max_instructions: Some(10_000),
+                ..Default::default()
+            });
+            if machine.run_to_halt(&[]).is_ok() && machine.get(0) == 19690720 {
                 return (100 * noun + verb).to_string();
             }
         }
@@ -20,45 +35,12 @@ pub fn entry_b(line: String) -> String {
     0.to_string()
 }
 
-fn to_instruction_vect(line: String) -> Vec<u32> {
-    line.split(",")
-        .map(|s| -> u32 { s.parse().unwrap() })
-        .collect()
-}
-
-fn run_ops(ops: &mut Vec<u32>) -> Result<(), &str> {
-    let mut cur = 0;
-    loop {
-        match ops.get(cur) {
-            Some(i) => {
-                match i {
-                    1 | 2 => {
-                        let opa_cur = *ops.get(cur + 1).unwrap() as usize;
-                        let opb_cur = *ops.get(cur + 2).unwrap() as usize;
-                        let result_cur = *ops.get(cur + 3).unwrap() as usize;
-                        if *i == 1 {
-                            *ops.get_mut(result_cur).unwrap() =
-                                ops.get(opa_cur).unwrap() + ops.get(opb_cur).unwrap()
-                        } else {
-                            *ops.get_mut(result_cur).unwrap() =
-                                ops.get(opa_cur).unwrap() * ops.get(opb_cur).unwrap()
-                        }
-                    }
-                    99 => break Ok(()),
-                    _ => return Err("Illegal opcode"),
-                }
-                cur += 4;
-            }
-            _ => return Err("Out of bound"),
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use super::entry_a;
+    use crate::intcode::{parse, Machine};
 
-    fn to_string(v: Vec<u32>) -> String {
+    fn to_string(v: Vec<i64>) -> String {
         let mut s = v
             .into_iter()
             .map(|u| u.to_string() + ",")
@@ -68,15 +50,15 @@ mod tests {
     }
 
     fn run(s: String) -> String {
-        let mut v = to_instruction_vect(s);
-        run_ops(&mut v).unwrap();
-        to_string(v)
+        let mut m = Machine::new(parse(&s));
+        m.run_to_halt(&[]).unwrap();
+        to_string(m.memory().to_vec())
     }
 
     #[test]
     fn to_from() {
         let s = String::from("1,2,3");
-        let v = to_instruction_vect(s.clone());
+        let v = parse(&s);
         assert_eq!(v, vec![1, 2, 3]);
         assert_eq!(to_string(v), s);
     }
@@ -91,4 +73,12 @@ mod tests {
             "30,1,1,4,2,5,6,0,99"
         );
     }
+
+    #[test]
+    fn test_plain_input() {
+        let input = include_str!("input.txt");
+        let plain: Vec<&str> = input.lines().filter(|l| !l.starts_with('#')).collect();
+        assert_eq!(entry_a(input.to_string()), "12490719");
+        assert_eq!(entry_a(plain.join("\n")), "12490719");
+    }
 }
@@ -0,0 +1,242 @@
+//! Text diagrams of wires, drawn like the puzzle examples:
+//!
+//! ```text
+//! ...........
+//! .+-----+...
+//! .|.....|...
+//! .|..+--X-+.
+//! .|..|..|.|.
+//! .|.-X--+.|.
+//! .|..|....|.
+//! .|.......|.
+//! .o-------+.
+//! ...........
+//! ```
+//!
+//! `-` and `|` are horizontal and vertical runs, `/` and `\` diagonals, `*` any other move, `+`
+//! a turn or a wire running over itself, `X` a point shared by several wires and `o` where a wire
+//! starts.
+//! Layers are drawn on top of each other.
+
+use std::collections::HashMap;
+
+use super::{Rect, Segment, Wire};
+
+/// How to draw a diagram
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Diagram {
+    /// Only draw the points within these bounds
+    pub crop: Option<Rect>,
+    /// Grid points per character, in both directions
+    pub scale: u32,
+    /// Empty characters around the drawing
+    pub margin: u32,
+}
+
+impl Default for Diagram {
+    fn default() -> Diagram {
+        Diagram {
+            crop: None,
+            scale: 1,
+            margin: 1,
+        }
+    }
+}
+
+/// What is drawn on a grid point
+#[derive(PartialEq, Debug, Copy, Clone)]
+struct Mark {
+    c: char,
+    /// Wire that drew it, several wires share the point once this is `None`
+    wire: Option<usize>,
+}
+
+/// Importance of a character when several points get drawn on the same one
+fn priority(c: char) -> u8 {
+    match c {
+        'o' => 4,
+        'X' => 3,
+        '+' => 2,
+        '.' => 0,
+        _ => 1,
+    }
+}
+
+fn line_char(s: &Segment) -> char {
+    let (dx, dy, dz) = s.unit();
+    match (dx, dy, dz) {
+        (_, 0, 0) => '-',
+        (0, _, 0) => '|',
+        (1, 1, 0) | (-1, -1, 0) => '/',
+        (1, -1, 0) | (-1, 1, 0) => '\\',
+        _ => '*',
+    }
+}
+
+impl Diagram {
+    /// The smallest scale showing every wire within `width` by `height` characters.
+    pub fn fit(wires: &[Wire], width: u32, height: u32) -> Diagram {
+        let segments: Vec<Vec<Segment>> = wires.iter().map(Wire::segments).collect();
+        let mut diagram = Diagram::default();
+        let ((x0, y0), (x1, y1)) = bounds(&segments);
+        let inner = |size: u32| size.saturating_sub(2 * diagram.margin).max(1);
+        let per_char = |extent: i32, size: u32| (extent as u32 + 1).div_ceil(inner(size));
+        diagram.scale = per_char(x1 - x0, width)
+            .max(per_char(y1 - y0, height))
+            .max(1);
+        diagram
+    }
+
+    /// Fails when the crop bounds are reversed.
+    pub fn render(&self, wires: &[Wire]) -> Result<String, String> {
+        let segments: Vec<Vec<Segment>> = wires.iter().map(Wire::segments).collect();
+        self.render_segments(&segments)
+    }
+
+    /// Draws wires given as lists of segments.
+    pub fn render_segments(&self, wires: &[Vec<Segment>]) -> Result<String, String> {
+        let ((x0, y0), (x1, y1)) = self.crop.unwrap_or_else(|| bounds(wires));
+        if x1 < x0 || y1 < y0 {
+            return Err(format!(
+                "Crop from ({}, {}) to ({}, {}) is reversed",
+                x0, y0, x1, y1
+            ));
+        }
+        let marks = marks(wires);
+        let scale = self.scale.max(1) as i32;
+        let margin = self.margin as i32;
+        // Character cells, cell (0, 0) holding the bottom left corner of the drawing
+        let (width, height) = ((x1 - x0) / scale + 1, (y1 - y0) / scale + 1);
+        let mut cells = vec![vec!['.'; width as usize]; height as usize];
+        for (&(x, y), mark) in &marks {
+            if x < x0 || x > x1 || y < y0 || y > y1 {
+                continue;
+            }
+            let cell = &mut cells[((y - y0) / scale) as usize][((x - x0) / scale) as usize];
+            if priority(mark.c) > priority(*cell) {
+                *cell = mark.c;
+            }
+        }
+        let blank = ".".repeat((width + 2 * margin) as usize);
+        let padding = ".".repeat(margin as usize);
+        let mut s = String::new();
+        for _ in 0..margin {
+            s.push_str(&blank);
+            s.push('\n');
+        }
+        for row in cells.iter().rev() {
+            s.push_str(&padding);
+            s.extend(row);
+            s.push_str(&padding);
+            s.push('\n');
+        }
+        for _ in 0..margin {
+            s.push_str(&blank);
+            s.push('\n');
+        }
+        Ok(s)
+    }
+}
+
+/// Bounds of the wires
+fn bounds(wires: &[Vec<Segment>]) -> Rect {
+    let mut points = wires.iter().flatten().flat_map(|s| [s.a, s.b]);
+    let first = points.next().map_or((0, 0), |p| (p.x, p.y));
+    points.fold((first, first), |((x0, y0), (x1, y1)), p| {
+        ((x0.min(p.x), y0.min(p.y)), (x1.max(p.x), y1.max(p.y)))
+    })
+}
+
+fn marks(wires: &[Vec<Segment>]) -> HashMap<(i32, i32), Mark> {
+    let mut marks: HashMap<(i32, i32), Mark> = HashMap::new();
+    let mut draw = |x: i32, y: i32, c: char, wire: usize| {
+        let mark = marks.entry((x, y)).or_insert(Mark {
+            c,
+            wire: Some(wire),
+        });
+        if mark.wire != Some(wire) {
+            *mark = Mark { c: 'X', wire: None };
+        } else if mark.c != c {
+            mark.c = '+';
+        }
+    };
+    for (w, segments) in wires.iter().enumerate() {
+        for (i, s) in segments.iter().enumerate() {
+            let c = line_char(s);
+            let points = s.points();
+            // Each segment draws its end, the next one starting there turns
+            let skip = if i == 0 { 0 } else { 1 };
+            for p in &points[skip..] {
+                draw(p.x, p.y, c, w);
+            }
+            if let Some(next) = segments.get(i + 1) {
+                if !next.is_empty() && line_char(next) != c {
+                    draw(s.b.x, s.b.y, '+', w);
+                }
+            }
+        }
+    }
+    for start in wires.iter().filter_map(|segments| segments.first()) {
+        marks.insert((start.a.x, start.a.y), Mark { c: 'o', wire: None });
+    }
+    marks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::split_input;
+    use super::*;
+
+    #[test]
+    fn test_example() {
+        let wires = split_input("R8,U5,L5,D3\nU7,R6,D4,L4");
+        assert_eq!(
+            Diagram::default().render(&wires).unwrap(),
+            "...........
+.+-----+...
+.|.....|...
+.|..+--X-+.
+.|..|..|.|.
+.|.-X--+.|.
+.|..|....|.
+.|.......|.
+.o-------+.
+...........
+"
+        );
+    }
+
+    #[test]
+    fn test_crop_and_scale() {
+        let wires = split_input("R8,U5,L5,D3\nU7,R6,D4,L4");
+        let crop = Diagram {
+            crop: Some(((2, 2), (5, 4))),
+            margin: 0,
+            ..Default::default()
+        };
+        assert_eq!(crop.render(&wires).unwrap(), ".|..\n-X--\n.|..\n");
+        let reversed = Diagram {
+            crop: Some(((5, 2), (2, 4))),
+            ..crop
+        };
+        assert!(reversed.render(&wires).is_err());
+
+        // The crossings win over the lines drawn in the same character
+        let fit = Diagram::fit(&wires, 7, 6);
+        assert_eq!(fit.scale, 2);
+        assert_eq!(
+            fit.render(&wires).unwrap(),
+            ".......\n.+--+..\n.|+-X+.\n.|X-+|.\n.o---+.\n.......\n"
+        );
+    }
+
+    #[test]
+    fn test_slanted() {
+        // The vector only has lattice points at its ends
+        let wires = split_input("UR2,R1,V-1:-2\nD1,R4");
+        assert_eq!(
+            Diagram::default().render(&wires).unwrap(),
+            ".......\n...++..\n../....\n.o.*...\n.+----.\n.......\n"
+        );
+    }
+}
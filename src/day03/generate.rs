@@ -0,0 +1,344 @@
+//! Random wires, to fuzz and benchmark the intersection code on inputs of any size.
+//!
+//! A pair of wires is built so that the number of crossings is known exactly. The bounding box
+//! is cut in three: the first wire wanders above the `y = 0` line, the second one below it,
+//! and a strip on the right is kept for the crossings. Once done wandering, the first wire
+//! leaves along the top edge and comes down to run along `y = 0` through the strip, while the
+//! second one leaves along the bottom edge and zigzags across that line once per crossing.
+//!
+//! ```text
+//! wander above   |+-+
+//! ---------------o-X-X--   first wire along y = 0
+//! wander below   | +-+     second wire zigzagging across it
+//! ```
+
+use super::{Direction, Rect, Wire};
+
+/// xorshift64*, good enough to generate test cases.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// xorshift only produces zeros from a zero state, a seed of 0 is replaced by another one
+    pub fn new(seed: u64) -> Rng {
+        Rng(if seed == 0 {
+            0x9e37_79b9_7f4a_7c15
+        } else {
+            seed
+        })
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in `lo..=hi`, `lo` must not be above `hi`
+    pub fn range(&mut self, lo: i32, hi: i32) -> i32 {
+        assert!(lo <= hi, "empty range {}..={}", lo, hi);
+        (lo as i64 + (self.next_u64() % (hi as i64 - lo as i64 + 1) as u64) as i64) as i32
+    }
+
+    /// Uniform in `[0, 1)`
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// How long each move is, before being cut short by the bounding box
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum StepSize {
+    Fixed(u32),
+    /// Uniform in `lo..=hi`
+    Uniform(u32, u32),
+    /// Geometric with the given mean, mostly short moves and a few long ones
+    Geometric(f64),
+}
+
+impl StepSize {
+    fn sample(&self, rng: &mut Rng) -> u32 {
+        match *self {
+            StepSize::Fixed(n) => n,
+            StepSize::Uniform(lo, hi) => rng.range(lo as i32, hi as i32) as u32,
+            StepSize::Geometric(mean) => {
+                let p = 1.0 / mean.max(1.0);
+                if p >= 1.0 {
+                    return 1;
+                }
+                // The tail goes past any mean, stay within what a move can be
+                let n = ((1.0 - rng.unit()).ln() / (1.0 - p).ln()).floor() as u32;
+                n.saturating_add(1).min(i32::MAX as u32)
+            }
+        }
+    }
+}
+
+/// Settings for a pair of random wires
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Generator {
+    /// Random moves of each wire, not counting the ones making the crossings
+    pub moves: usize,
+    pub steps: StepSize,
+    /// Box holding both wires, it must contain the origin away from its edges
+    pub bounds: Rect,
+    /// Number of crossings between the two wires, their shared start left aside
+    pub crossings: usize,
+    /// Whether to also move diagonally
+    pub diagonals: bool,
+}
+
+impl Default for Generator {
+    fn default() -> Generator {
+        Generator {
+            moves: 300,
+            steps: StepSize::Uniform(1, 1_000),
+            bounds: ((-10_000, -10_000), (10_000, 10_000)),
+            crossings: 10,
+            diagonals: false,
+        }
+    }
+}
+
+/// Moves within a layer, the diagonal ones last
+const MOVES: [Direction; 8] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Right,
+    Direction::Left,
+    Direction::UpRight,
+    Direction::UpLeft,
+    Direction::DownRight,
+    Direction::DownLeft,
+];
+
+/// A wire being built, as text
+struct Path {
+    x: i32,
+    y: i32,
+    moves: Vec<String>,
+}
+
+impl Path {
+    fn new() -> Path {
+        Path {
+            x: 0,
+            y: 0,
+            moves: vec![],
+        }
+    }
+
+    fn go(&mut self, m: Direction, n: i32) {
+        if n > 0 {
+            let (dx, dy, _) = m.delta();
+            self.x += dx * n;
+            self.y += dy * n;
+            self.moves.push(format!("{}{}", m, n));
+        }
+    }
+
+    /// Goes straight to `x` then `y`
+    fn go_to(&mut self, x: i32, y: i32) {
+        let (dx, dy) = (x - self.x, y - self.y);
+        let (horizontal, vertical) = (
+            if dx > 0 {
+                Direction::Right
+            } else {
+                Direction::Left
+            },
+            if dy > 0 {
+                Direction::Up
+            } else {
+                Direction::Down
+            },
+        );
+        self.go(horizontal, dx.abs());
+        self.go(vertical, dy.abs());
+    }
+
+    /// Random moves staying within `area`
+    fn wander(&mut self, rng: &mut Rng, g: &Generator, area: Rect) {
+        let ((x0, y0), (x1, y1)) = area;
+        let kinds = if g.diagonals { 8 } else { 4 };
+        for _ in 0..g.moves {
+            // Room left in the chosen direction, some direction has room unless the area is a
+            // single point
+            for _ in 0..8 {
+                let m = MOVES[rng.range(0, kinds - 1) as usize];
+                let (dx, dy, _) = m.delta();
+                let room = |p: i32, d: i32, lo: i32, hi: i32| match d {
+                    1 => hi - p,
+                    -1 => p - lo,
+                    _ => i32::MAX,
+                };
+                let room = room(self.x, dx, x0, x1).min(room(self.y, dy, y0, y1));
+                let n = (g.steps.sample(rng) as i32).min(room);
+                if n > 0 {
+                    self.go(m, n);
+                    break;
+                }
+            }
+        }
+    }
+
+    fn wire(&self) -> Wire {
+        Wire::from(self.moves.join(",").as_str())
+    }
+}
+
+impl Generator {
+    /// Two wires starting at the origin and crossing exactly `crossings` times. Fails when the
+    /// bounds are too small, or the step sizes are out of order or can't fit in an `i32`.
+    pub fn pair(&self, rng: &mut Rng) -> Result<(Wire, Wire), String> {
+        let largest = i32::MAX as u32;
+        let valid = match self.steps {
+            StepSize::Fixed(n) => n <= largest,
+            StepSize::Uniform(lo, hi) => lo <= hi && hi <= largest,
+            StepSize::Geometric(mean) => mean.is_finite() && mean <= largest as f64,
+        };
+        if !valid {
+            return Err(format!("{:?} is not a valid step size", self.steps));
+        }
+        let ((x0, y0), (x1, y1)) = self.bounds;
+        // The strip on the right holds the crossings, at strip + 1, strip + 3...
+        let strip = x1 - 2 * self.crossings as i32;
+        if !(x0 <= 0 && 0 < strip && y0 < 0 && 0 < y1) {
+            return Err(format!(
+                "{:?} is too small for {} crossings around the origin",
+                self.bounds, self.crossings
+            ));
+        }
+
+        let mut first = Path::new();
+        first.go(Direction::Up, 1);
+        first.wander(rng, self, ((x0, 1), (strip - 1, y1)));
+        first.go_to(first.x, y1);
+        first.go_to(strip, y1);
+        first.go_to(strip, 0);
+        first.go_to(x1, 0);
+
+        let mut second = Path::new();
+        second.go(Direction::Down, 1);
+        second.wander(rng, self, ((x0, y0), (strip - 1, -1)));
+        second.go_to(second.x, y0);
+        if self.crossings > 0 {
+            second.go_to(strip + 1, y0);
+        }
+        for i in 0..self.crossings as i32 {
+            if i > 0 {
+                second.go(Direction::Right, 2);
+            }
+            second.go_to(second.x, if i & 1 == 0 { 1 } else { -1 });
+        }
+        Ok((first.wire(), second.wire()))
+    }
+
+    /// A pair of wires in the puzzle input format
+    pub fn input(&self, rng: &mut Rng) -> Result<String, String> {
+        let (first, second) = self.pair(rng)?;
+        Ok(format!("{}\n{}", first, second))
+    }
+}
+
+/// Moves picked by `random_moves`, the first kinds first
+#[cfg(test)]
+const RANDOM_MOVES: [&str; 10] = ["U", "D", "L", "R", "UR", "DL", "F", "B", "UL", "DR"];
+
+/// `count` moves of up to `max_len` steps, of the first `kinds` of `RANDOM_MOVES`. One kind
+/// more adds vectors with coordinates up to `max_len`.
+#[cfg(test)]
+pub(super) fn random_moves(rng: &mut Rng, count: usize, max_len: i32, kinds: usize) -> Vec<String> {
+    (0..count)
+        .map(|_| match rng.range(0, kinds as i32 - 1) as usize {
+            k if k == RANDOM_MOVES.len() => format!(
+                "V{}:{}",
+                rng.range(-max_len, max_len),
+                rng.range(-max_len, max_len)
+            ),
+            k => format!("{}{}", RANDOM_MOVES[k], rng.range(0, max_len)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{crossings, split_input};
+    use super::*;
+
+    #[test]
+    fn test_exact_crossings() {
+        let mut rng = Rng::new(99);
+        for &(moves, crossings_wanted, diagonals) in &[(0, 0, false), (20, 3, false), (50, 8, true)]
+        {
+            let g = Generator {
+                moves,
+                steps: StepSize::Geometric(4.0),
+                bounds: ((-30, -20), (40, 20)),
+                crossings: crossings_wanted,
+                diagonals,
+            };
+            let input = g.input(&mut rng).unwrap();
+            let wires = split_input(&input);
+            let found: Vec<_> = crossings(&wires)
+                .into_iter()
+                .filter(|c| !c.is_start())
+                .collect();
+            assert_eq!(found.len(), crossings_wanted, "{}", input);
+            for w in &wires {
+                for s in w.segments() {
+                    for p in [s.a, s.b] {
+                        assert!((-30..=40).contains(&p.x) && (-20..=20).contains(&p.y));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_settings() {
+        let mut rng = Rng::new(5);
+        let fixed = Generator {
+            moves: 10,
+            steps: StepSize::Fixed(3),
+            crossings: 1,
+            ..Default::default()
+        };
+        let (first, _) = fixed.pair(&mut rng).unwrap();
+        let text = first.to_string();
+        let wandering: Vec<&str> = text.split(',').skip(1).take(10).collect();
+        assert!(wandering.iter().all(|m| m.ends_with('3')), "{}", text);
+
+        let too_small = Generator {
+            bounds: ((-5, -5), (5, 5)),
+            crossings: 3,
+            ..Default::default()
+        };
+        assert!(too_small.pair(&mut rng).is_err());
+        let reversed = Generator {
+            steps: StepSize::Uniform(5, 2),
+            ..Default::default()
+        };
+        assert!(reversed.pair(&mut rng).is_err());
+        for steps in [
+            StepSize::Fixed(1 << 31),
+            StepSize::Geometric(5e9),
+            StepSize::Geometric(f64::NAN),
+        ] {
+            let g = Generator {
+                steps,
+                ..Default::default()
+            };
+            assert!(g.pair(&mut rng).is_err(), "{:?}", steps);
+        }
+        let huge = StepSize::Geometric(i32::MAX as f64);
+        assert!((0..1_000).all(|_| huge.sample(&mut rng) <= i32::MAX as u32));
+        assert_ne!(Rng::new(0).next_u64(), 0);
+
+        let mean: f64 = (0..10_000)
+            .map(|_| StepSize::Geometric(5.0).sample(&mut rng) as f64)
+            .sum::<f64>()
+            / 10_000.0;
+        assert!((4.8..5.2).contains(&mean), "{}", mean);
+    }
+}
@@ -0,0 +1,203 @@
+//! Reference solver walking every step of the wires, with none of the geometry. It is slow and
+//! uses memory in proportion to the length of the wires, but simple enough to check the
+//! segment-based solver against.
+
+use std::collections::{BTreeMap, HashMap};
+
+use super::{lattice_steps, Crossing, Point, Wire};
+
+/// Steps the wire takes to first reach each of its points
+pub fn walk(wire: &Wire) -> HashMap<Point, u32> {
+    let mut p = wire.start;
+    let mut steps = 0;
+    let mut visited = HashMap::new();
+    visited.insert(p, 0);
+    for (direction, count) in &wire.v {
+        let (dx, dy, dz) = direction.delta();
+        let n = *count as i32;
+        // Vectors only stop on the lattice points along them
+        let k = lattice_steps((dx * n, dy * n, dz * n)) as i32;
+        for _ in 0..k {
+            p = Point {
+                x: p.x + dx * n / k,
+                y: p.y + dy * n / k,
+                z: p.z + dz * n / k,
+            };
+            steps += 1;
+            visited.entry(p).or_insert(steps);
+        }
+    }
+    visited
+}
+
+/// Every point where at least two wires meet, ordered by coordinates.
+pub fn crossings(wires: &[Wire]) -> Vec<Crossing> {
+    let mut by_point: HashMap<Point, BTreeMap<usize, u32>> = HashMap::new();
+    for (w, wire) in wires.iter().enumerate() {
+        for (p, steps) in walk(wire) {
+            by_point.entry(p).or_default().insert(w, steps);
+        }
+    }
+    let mut crossings: Vec<Crossing> = by_point
+        .into_iter()
+        .filter(|(_, wires)| wires.len() >= 2)
+        .map(|(point, wires)| Crossing {
+            point,
+            wires: wires.into_iter().collect(),
+        })
+        .collect();
+    crossings.sort_by_key(|c| (c.point.x, c.point.y, c.point.z));
+    crossings
+}
+
+/// Manhattan distance from the origin to the closest crossing
+pub fn closest_distance(wires: &[Wire]) -> Option<u32> {
+    crossings(wires)
+        .iter()
+        .filter(|c| !c.is_start())
+        .map(|c| c.point.distance_from_origin())
+        .min()
+}
+
+/// Fewest combined steps to a crossing
+pub fn fewest_steps(wires: &[Wire]) -> Option<u32> {
+    crossings(wires)
+        .iter()
+        .filter(|c| !c.is_start())
+        .map(|c| c.combined_steps())
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::ascii::Diagram;
+    use super::super::generate::{random_moves, Rng};
+    use super::super::{best_crossing, split_input, Criterion};
+    use super::*;
+
+    /// Wires as lists of moves, easy to shrink
+    type Case = Vec<Vec<String>>;
+
+    fn wires(case: &Case) -> Vec<Wire> {
+        case.iter()
+            .map(|moves| Wire::from(moves.join(",").as_str()))
+            .collect()
+    }
+
+    /// How the two solvers disagree on a case, if they do
+    fn disagreement(case: &Case) -> Option<String> {
+        let wires = wires(case);
+        let (expected, found) = (crossings(&wires), super::super::crossings(&wires));
+        if found != expected {
+            return Some(format!("crossings {:?}, expected {:?}", found, expected));
+        }
+        let closest =
+            best_crossing(&wires, Criterion::Distance).map(|c| c.point.distance_from_origin());
+        if closest != closest_distance(&wires) {
+            return Some(format!(
+                "closest {:?}, expected {:?}",
+                closest,
+                closest_distance(&wires)
+            ));
+        }
+        let fastest = best_crossing(&wires, Criterion::Steps).map(|c| c.combined_steps());
+        if fastest != fewest_steps(&wires) {
+            return Some(format!(
+                "fewest steps {:?}, expected {:?}",
+                fastest,
+                fewest_steps(&wires)
+            ));
+        }
+        None
+    }
+
+    /// Smaller versions of a case: a move left out, or a shorter move
+    fn simpler(case: &Case) -> Vec<Case> {
+        let mut candidates = vec![];
+        for (w, moves) in case.iter().enumerate() {
+            for (i, m) in moves.iter().enumerate() {
+                if moves.len() > 1 {
+                    let mut c = case.clone();
+                    c[w].remove(i);
+                    candidates.push(c);
+                }
+                let split = m.find(|c: char| c.is_ascii_digit()).unwrap_or(m.len());
+                if let (false, Ok(n)) = (m.starts_with('V'), m[split..].parse::<u32>()) {
+                    for shorter in [n / 2, n.saturating_sub(1)] {
+                        if shorter < n {
+                            let mut c = case.clone();
+                            c[w][i] = format!("{}{}", &m[..split], shorter);
+                            candidates.push(c);
+                        }
+                    }
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Simplifies a failing case for as long as it keeps failing
+    fn shrink<F>(mut case: Case, fails: F) -> Case
+    where
+        F: Fn(&Case) -> bool,
+    {
+        while let Some(smaller) = simpler(&case).into_iter().find(|c| fails(c)) {
+            case = smaller;
+        }
+        case
+    }
+
+    fn random_case(rng: &mut Rng) -> Case {
+        (0..2)
+            .map(|_| {
+                let count = rng.range(1, 8) as usize;
+                random_moves(rng, count, 6, 11)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reference() {
+        let wires = split_input("R8,U5,L5,D3\nU7,R6,D4,L4");
+        assert_eq!(walk(&wires[0])[&Point::new(3, 3)], 20);
+        assert_eq!(closest_distance(&wires), Some(6));
+        assert_eq!(fewest_steps(&wires), Some(30));
+        assert_eq!(fewest_steps(&split_input("U1\nR1")), None);
+    }
+
+    #[test]
+    fn test_shrink() {
+        let case = vec![
+            vec![String::from("U5"), String::from("R7"), String::from("U3")],
+            vec![String::from("L2")],
+        ];
+        // Stand-in bug: going up at least 4 steps in all
+        let fails = |c: &Case| wires(c)[0].segments().last().unwrap().b.y >= 4;
+        // No move can be dropped or shortened any more
+        assert_eq!(
+            shrink(case, fails),
+            vec![
+                vec![String::from("U1"), String::from("U3")],
+                vec![String::from("L0")]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_against_reference() {
+        let mut rng = Rng::new(0x0dd);
+        for _ in 0..2_000 {
+            let case = random_case(&mut rng);
+            if disagreement(&case).is_some() {
+                let minimal = shrink(case, |c| disagreement(c).is_some());
+                let lines: Vec<String> = minimal.iter().map(|moves| moves.join(",")).collect();
+                panic!(
+                    "solvers disagree on\n{}\n{}\n{}",
+                    lines.join("\n"),
+                    disagreement(&minimal).unwrap(),
+                    Diagram::default().render(&wires(&minimal)).unwrap()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Where a single wire crosses or runs over itself.
+
+use super::{segments_intersect, steps_before, sweep, Point, Wire};
+
+/// The wire coming back to a point it already went through.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Revisit {
+    pub point: Point,
+    /// Steps to the first visit
+    pub first: u32,
+    /// Steps to the second visit
+    pub second: u32,
+}
+
+impl Revisit {
+    /// Steps saved by going straight from the first visit to the second one
+    pub fn loop_len(&self) -> u32 {
+        self.second - self.first
+    }
+}
+
+impl Wire {
+    /// Every pair of visits to a point the wire goes through more than once, in the order the
+    /// wire gets back to them.
+    pub fn self_intersections(&self) -> Vec<Revisit> {
+        let segments = self.segments();
+        let steps = steps_before(&segments);
+        let mut revisits = vec![];
+        for (i, j) in sweep::touching_pairs(&segments, &segments) {
+            if i >= j {
+                continue;
+            }
+            let (s1, s2) = (&segments[i], &segments[j]);
+            for point in segments_intersect(s1, s2) {
+                let first = steps[i] + s1.steps_to(&point);
+                let second = steps[j] + s2.steps_to(&point);
+                // Consecutive segments share their end, that is the same visit
+                if first != second {
+                    revisits.push(Revisit {
+                        point,
+                        first: first.min(second),
+                        second: first.max(second),
+                    });
+                }
+            }
+        }
+        revisits.sort_by_key(|r| (r.second, r.first, r.point.x, r.point.y, r.point.z));
+        revisits.dedup();
+        revisits
+    }
+
+    /// The first time the wire gets back to a point it already visited
+    pub fn first_revisit(&self) -> Option<Revisit> {
+        self.self_intersections().into_iter().next()
+    }
+
+    /// The loop that can be cut out with the fewest steps saved, earliest one on ties
+    pub fn shortest_loop(&self) -> Option<Revisit> {
+        self.self_intersections()
+            .into_iter()
+            .min_by_key(|r| (r.loop_len(), r.second))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_loop() {
+        let w = Wire::from("R8,U5,L5,D3");
+        assert_eq!(w.self_intersections(), vec![]);
+        assert_eq!(w.first_revisit(), None);
+        assert_eq!(w.shortest_loop(), None);
+    }
+
+    #[test]
+    fn test_crossing_itself() {
+        let w = Wire::from("R5,U2,L2,D4,L5,U10,R1,D13");
+        assert_eq!(
+            w.self_intersections(),
+            vec![
+                Revisit {
+                    point: Point::new(3, 0),
+                    first: 3,
+                    second: 11
+                },
+                Revisit {
+                    point: Point::new(-1, -2),
+                    first: 17,
+                    second: 39
+                }
+            ]
+        );
+        assert_eq!(w.first_revisit().unwrap().point, Point::new(3, 0));
+        assert_eq!(w.shortest_loop().unwrap().loop_len(), 8);
+    }
+
+    #[test]
+    fn test_backtrack() {
+        let w = Wire::from("U5,D3");
+        let revisits = w.self_intersections();
+        assert_eq!(revisits.len(), 3);
+        assert_eq!(
+            w.first_revisit(),
+            Some(Revisit {
+                point: Point::new(0, 4),
+                first: 4,
+                second: 6
+            })
+        );
+        assert_eq!(w.shortest_loop().unwrap().loop_len(), 2);
+    }
+}
@@ -0,0 +1,59 @@
+//! Distances used to pick the closest crossing.
+
+use super::Point;
+
+pub trait Metric {
+    fn distance(&self, a: &Point, b: &Point) -> f64;
+}
+
+/// Sum of the differences along each axis, the length of a path following the grid
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Manhattan;
+
+/// Largest difference along an axis, diagonal moves counting as one step
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Chebyshev;
+
+/// Straight line distance
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Euclidean;
+
+fn differences(a: &Point, b: &Point) -> [f64; 3] {
+    [
+        (a.x as f64 - b.x as f64).abs(),
+        (a.y as f64 - b.y as f64).abs(),
+        (a.z as f64 - b.z as f64).abs(),
+    ]
+}
+
+impl Metric for Manhattan {
+    fn distance(&self, a: &Point, b: &Point) -> f64 {
+        a.distance_from(b) as f64
+    }
+}
+
+impl Metric for Chebyshev {
+    fn distance(&self, a: &Point, b: &Point) -> f64 {
+        differences(a, b).iter().copied().fold(0.0, f64::max)
+    }
+}
+
+impl Metric for Euclidean {
+    fn distance(&self, a: &Point, b: &Point) -> f64 {
+        differences(a, b).iter().map(|d| d * d).sum::<f64>().sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics() {
+        let (a, b) = (Point::new(1, -2), Point { x: 4, y: 2, z: 1 });
+        assert_eq!(Manhattan.distance(&a, &b), 8.0);
+        assert_eq!(Chebyshev.distance(&a, &b), 4.0);
+        assert_eq!(Euclidean.distance(&a, &b), 26f64.sqrt());
+        assert_eq!(Euclidean.distance(&b, &a), Euclidean.distance(&a, &b));
+    }
+}
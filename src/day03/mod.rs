@@ -1,95 +1,261 @@
 use std::cmp::{max, min};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
 
-#[derive(PartialEq, Debug)]
-enum Direction {
+pub mod ascii;
+pub mod generate;
+pub mod grid;
+mod loops;
+pub mod metric;
+mod normalize;
+mod query;
+pub mod report;
+pub mod route;
+pub mod spatial;
+pub mod stream;
+pub mod svg;
+mod sweep;
+
+pub use self::loops::Revisit;
+use self::metric::{Manhattan, Metric};
+pub use self::normalize::Normalized;
+pub use self::query::WireIndex;
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Direction {
     Up,
     Down,
     Right,
     Left,
+    UpRight,
+    UpLeft,
+    DownRight,
+    DownLeft,
+    /// One layer up
+    Forward,
+    /// One layer down
+    Back,
+    /// Arbitrary move, written `V<dx>:<dy>` or `V<dx>:<dy>:<dz>`
+    Vector(i32, i32, i32),
 }
 
 impl Direction {
-    fn from(s: &str) -> Direction {
+    fn parse(s: &str) -> Option<Direction> {
         use Direction::*;
-        match s {
+        Some(match s {
             "U" => Up,
             "D" => Down,
             "R" => Right,
             "L" => Left,
-            _ => panic!("Unrecognized direction {}", s),
+            "UR" => UpRight,
+            "UL" => UpLeft,
+            "DR" => DownRight,
+            "DL" => DownLeft,
+            "F" => Forward,
+            "B" => Back,
+            _ => return None,
+        })
+    }
+
+    /// Displacement of a single move
+    pub fn delta(&self) -> (i32, i32, i32) {
+        use Direction::*;
+        match *self {
+            Up => (0, 1, 0),
+            Down => (0, -1, 0),
+            Right => (1, 0, 0),
+            Left => (-1, 0, 0),
+            UpRight => (1, 1, 0),
+            UpLeft => (-1, 1, 0),
+            DownRight => (1, -1, 0),
+            DownLeft => (-1, -1, 0),
+            Forward => (0, 0, 1),
+            Back => (0, 0, -1),
+            Vector(dx, dy, dz) => (dx, dy, dz),
         }
     }
 }
 
-#[derive(PartialEq, Debug, Copy, Clone)]
-struct Point {
-    x: i32,
-    y: i32,
+/// Inclusive `((x0, y0), (x1, y1))` bounds, in every layer
+pub type Rect = ((i32, i32), (i32, i32));
+
+/// A point of the grid, `z` is the layer and stays 0 for flat wires.
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
 }
 
 impl Point {
-    fn distance_from_origin(&self) -> u32 {
-        self.distance_from(&Point { x: 0, y: 0 })
+    pub const ORIGIN: Point = Point::new(0, 0);
+
+    /// A point of the `z = 0` layer
+    pub const fn new(x: i32, y: i32) -> Point {
+        Point { x, y, z: 0 }
     }
 
-    fn distance_from(&self, other: &Point) -> u32 {
-        (self.x - other.x).abs() as u32 + (self.y - other.y).abs() as u32
+    pub fn distance_from_origin(&self) -> u32 {
+        self.distance_from(&Point::ORIGIN)
+    }
+
+    pub fn distance_from(&self, other: &Point) -> u32 {
+        (self.x - other.x).unsigned_abs()
+            + (self.y - other.y).unsigned_abs()
+            + (self.z - other.z).unsigned_abs()
     }
-}
 
-#[derive(Debug)]
-struct PointWithSteps {
-    point: Point,
-    steps: u32,
+    /// Displacement from this point to `other`
+    fn to(&self, other: &Point) -> (i32, i32, i32) {
+        (other.x - self.x, other.y - self.y, other.z - self.z)
+    }
 }
 
 #[derive(PartialEq, Debug)]
-struct Segment {
-    a: Point,
-    b: Point,
+pub struct PointWithSteps {
+    pub point: Point,
+    pub steps: u32,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Segment {
+    pub a: Point,
+    pub b: Point,
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Number of lattice steps of a displacement
+fn lattice_steps((dx, dy, dz): (i32, i32, i32)) -> u32 {
+    gcd(gcd(dx.unsigned_abs(), dy.unsigned_abs()), dz.unsigned_abs())
+}
+
+/// Vectors are widened so that products of cross products cannot overflow
+type Wide = (i128, i128, i128);
+
+fn wide(u: (i32, i32, i32)) -> Wide {
+    (u.0 as i128, u.1 as i128, u.2 as i128)
+}
+
+fn cross(u: Wide, v: Wide) -> Wide {
+    (
+        u.1 * v.2 - u.2 * v.1,
+        u.2 * v.0 - u.0 * v.2,
+        u.0 * v.1 - u.1 * v.0,
+    )
+}
+
+fn dot(u: Wide, v: Wide) -> i128 {
+    u.0 * v.0 + u.1 * v.1 + u.2 * v.2
 }
 
 impl Segment {
-    fn len(&self) -> u32 {
-        (self.a.x - self.b.x).abs() as u32 + (self.a.y - self.b.y).abs() as u32
+    /// Number of steps along the segment, a step going from one lattice point of the segment to
+    /// the next. This is the length for horizontal, vertical and diagonal segments.
+    pub fn len(&self) -> u32 {
+        lattice_steps(self.a.to(&self.b))
+    }
+
+    /// Displacement of a single step, `(0, 0, 0)` for an empty segment
+    fn unit(&self) -> (i32, i32, i32) {
+        let n = self.len() as i32;
+        if n == 0 {
+            return (0, 0, 0);
+        }
+        let (dx, dy, dz) = self.a.to(&self.b);
+        (dx / n, dy / n, dz / n)
+    }
+
+    /// Point reached after `n` steps
+    fn at(&self, n: i32) -> Point {
+        let (dx, dy, dz) = self.unit();
+        Point {
+            x: self.a.x + n * dx,
+            y: self.a.y + n * dy,
+            z: self.a.z + n * dz,
+        }
+    }
+
+    /// Steps from `a` to a point of the segment
+    pub fn steps_to(&self, p: &Point) -> u32 {
+        lattice_steps(self.a.to(p))
+    }
+
+    /// Horizontal or vertical, within a single layer
+    fn is_axis_aligned(&self) -> bool {
+        self.a.z == self.b.z && (self.a.x == self.b.x || self.a.y == self.b.y)
+    }
+
+    /// Whether the segment is a single point
+    pub fn is_empty(&self) -> bool {
+        self.a == self.b
+    }
+
+    /// Every lattice point from `a` to `b`, both included.
+    fn points(&self) -> Vec<Point> {
+        (0..=self.len() as i32).map(|i| self.at(i)).collect()
     }
 }
 
 #[derive(PartialEq, Debug)]
-struct Wire {
+pub struct Wire {
     v: Vec<(Direction, u32)>,
+    start: Point,
 }
 
 impl Wire {
-    fn from(s: &str) -> Wire {
-        Wire {
-            v: s.split(",").map(|s| decode_step(s)).collect(),
+    /// A wire starting at the origin, an empty string being a wire without any move
+    pub fn from(s: &str) -> Wire {
+        Wire::parse(s).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Same as `from`, for input that may be malformed or leave the `i32` range
+    pub fn parse(s: &str) -> Result<Wire, String> {
+        let mut v = vec![];
+        let mut current = Point::ORIGIN;
+        let tokens = if s.is_empty() {
+            vec![]
+        } else {
+            s.split(",").collect()
+        };
+        for token in tokens {
+            let step = parse_step(token)?;
+            current = step_end(current, step)
+                .ok_or_else(|| format!("Move {:?} goes out of range", token))?;
+            v.push(step);
         }
+        Ok(Wire {
+            v,
+            start: Point::ORIGIN,
+        })
+    }
+
+    /// The same moves from another start
+    pub fn starting_at(self, start: Point) -> Wire {
+        Wire { start, ..self }
+    }
+
+    pub fn start(&self) -> Point {
+        self.start
     }
 }
 
 impl Wire {
-    fn segments(&self) -> Vec<(Segment)> {
-        let mut current = Point { x: 0, y: 0 };
+    pub fn segments(&self) -> Vec<Segment> {
+        let mut current = self.start;
         let mut segments = vec![];
         for e in &self.v {
-            let next = match e.0 {
-                Direction::Up => Point {
-                    x: current.x,
-                    y: current.y + e.1 as i32,
-                },
-                Direction::Down => Point {
-                    x: current.x,
-                    y: current.y - e.1 as i32,
-                },
-                Direction::Right => Point {
-                    x: current.x + e.1 as i32,
-                    y: current.y,
-                },
-                Direction::Left => Point {
-                    x: current.x - e.1 as i32,
-                    y: current.y,
-                },
+            let (dx, dy, dz) = e.0.delta();
+            let next = Point {
+                x: current.x + dx * e.1 as i32,
+                y: current.y + dy * e.1 as i32,
+                z: current.z + dz * e.1 as i32,
             };
             segments.push(Segment {
                 a: current,
@@ -101,116 +267,308 @@ impl Wire {
     }
 }
 
-/// Do segments intersect? and if so where? We can simplify the logic because everything is vertical/horizontal.
-/// We can have multiple intersection if the wires run along
+/// Do segments intersect? and if so where? Only lattice points are considered, diagonal
+/// segments going through each other between two lattice points do not intersect.
+/// We can have multiple intersection if the wires run along, then every shared point is returned.
 fn segments_intersect(seg1: &Segment, seg2: &Segment) -> Vec<Point> {
-    let mut intersects = vec![];
-    if max(seg1.a.x, seg1.b.x) < min(seg2.a.x, seg2.b.x)
-        || min(seg1.a.x, seg1.b.x) > max(seg2.a.x, seg2.b.x)
-    {
-        return intersects;
-    }
-    if max(seg1.a.y, seg1.b.y) < min(seg2.a.y, seg2.b.y)
-        || min(seg1.a.y, seg1.b.y) > max(seg2.a.y, seg2.b.y)
-    {
-        return intersects;
+    match overlap(seg1, seg2) {
+        Some(o) => o.points(),
+        None => vec![],
     }
+}
 
-    if seg1.a.x == seg1.b.x && seg2.a.x == seg2.b.x {
-        if seg1.a.x != seg2.a.x {
-            panic!("Should not happen");
-        }
-        for y in max(min(seg1.a.y, seg1.b.y), min(seg2.a.y, seg2.b.y))
-            ..min(max(seg1.a.y, seg1.b.y), max(seg2.a.y, seg2.b.y))
-        {
-            intersects.push(Point { x: seg1.a.x, y })
-        }
-        return intersects;
+/// The part two segments have in common, going the same way as `seg1`: a single point when
+/// they cross, a sub-segment when they run along each other. Everything is computed on the
+/// lattice steps of the segments so it stays exact.
+fn overlap(seg1: &Segment, seg2: &Segment) -> Option<Segment> {
+    if seg1.is_empty() || seg2.is_empty() {
+        let (point, other) = if seg1.is_empty() {
+            (seg1.a, seg2)
+        } else {
+            (seg2.a, seg1)
+        };
+        return overlap_point(&point, other);
     }
-    if seg1.a.y == seg1.b.y && seg2.a.y == seg2.b.y {
-        if seg1.a.y != seg2.a.y {
-            panic!("Should not happen");
+    let (u, v) = (wide(seg1.unit()), wide(seg2.unit()));
+    let (n1, n2) = (seg1.len() as i128, seg2.len() as i128);
+    let w = wide(seg1.a.to(&seg2.a));
+    let normal = cross(u, v);
+    if normal != (0, 0, 0) {
+        if dot(w, normal) != 0 {
+            // Skew lines, they are not in a common plane
+            return None;
         }
-        for y in max(min(seg1.a.x, seg1.b.x), min(seg2.a.x, seg2.b.x))
-            ..min(max(seg1.a.x, seg1.b.x), max(seg2.a.x, seg2.b.x))
-        {
-            intersects.push(Point { x: seg1.a.y, y })
+        // Solve seg1.a + t u = seg2.a + s v
+        let denom = dot(normal, normal);
+        let (t, s) = (dot(cross(w, v), normal), dot(cross(w, u), normal));
+        if t % denom != 0 || s % denom != 0 {
+            return None;
         }
-        return intersects;
+        let (t, s) = (t / denom, s / denom);
+        if t < 0 || t > n1 || s < 0 || s > n2 {
+            return None;
+        }
+        let p = seg1.at(t as i32);
+        return Some(Segment { a: p, b: p });
+    }
+    if cross(w, u) != (0, 0, 0) {
+        // Parallel but not on the same line
+        return None;
+    }
+    // Same line, v is u or -u and seg2.a is k steps of u away from seg1.a
+    let k0 = dot(w, u) / dot(u, u);
+    let k1 = k0 + n2 * dot(u, v).signum();
+    let (lo, hi) = (max(0, min(k0, k1)), min(n1, max(k0, k1)));
+    if lo > hi {
+        return None;
     }
-    if seg1.a.x == seg1.b.x {
-        return vec![Point {
-            x: seg1.a.x,
-            y: seg2.a.y,
-        }];
+    Some(Segment {
+        a: seg1.at(lo as i32),
+        b: seg1.at(hi as i32),
+    })
+}
+
+fn overlap_point(p: &Point, seg: &Segment) -> Option<Segment> {
+    let w = wide(seg.a.to(p));
+    let within = |a: i32, b: i32, c: i32| min(a, b) <= c && c <= max(a, b);
+    let on_line = if seg.is_empty() {
+        *p == seg.a
+    } else {
+        cross(w, wide(seg.unit())) == (0, 0, 0)
+            && within(seg.a.x, seg.b.x, p.x)
+            && within(seg.a.y, seg.b.y, p.y)
+            && within(seg.a.z, seg.b.z, p.z)
+    };
+    if on_line {
+        Some(Segment { a: *p, b: *p })
     } else {
-        return vec![Point {
-            x: seg2.a.x,
-            y: seg1.a.y,
-        }];
+        None
     }
 }
 
-/// Decodes a step
+/// Decodes a step, the direction is one or two letters, or a `V` followed by a vector whose
+/// `z` can be left out. A count beyond `i32::MAX` is an error, it could not be walked.
+/// ```ignore
+/// assert_eq!(parse_step("D12"), Ok((Direction::Down, 12)));
+/// assert_eq!(parse_step("UR3"), Ok((Direction::UpRight, 3)));
+/// assert_eq!(parse_step("V3:-2"), Ok((Direction::Vector(3, -2, 0), 1)));
+/// assert_eq!(parse_step("V3:-2:1"), Ok((Direction::Vector(3, -2, 1), 1)));
 /// ```
-/// assert_eq!(decode_step("Da12"), (Direction::Down, 12))
-/// ```
-fn decode_step(s: &str) -> (Direction, u32) {
-    (Direction::from(&s[0..1]), s[1..].parse().unwrap())
+fn parse_step(s: &str) -> Result<(Direction, u32), String> {
+    let bad = || format!("Bad move {:?}", s);
+    if let Some(v) = s.strip_prefix('V') {
+        let d = v
+            .split(':')
+            .map(|c| c.parse().map_err(|_| bad()))
+            .collect::<Result<Vec<i32>, String>>()?;
+        return match d[..] {
+            [dx, dy] => Ok((Direction::Vector(dx, dy, 0), 1)),
+            [dx, dy, dz] => Ok((Direction::Vector(dx, dy, dz), 1)),
+            _ => Err(bad()),
+        };
+    }
+    let split = s
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(s.len());
+    let direction = Direction::parse(&s[..split]).ok_or_else(bad)?;
+    let count: u32 = s[split..].parse().map_err(|_| bad())?;
+    if count > i32::MAX as u32 {
+        return Err(format!("Move {:?} is too long", s));
+    }
+    Ok((direction, count))
 }
 
-/// Splits the input in 2 wires
-fn split_input(s: &str) -> Vec<Wire> {
-    s.split("\n").map(|s| Wire::from(s)).collect()
+/// Where a move from `p` ends, `None` when that is out of the `i32` range
+fn step_end(p: Point, (direction, count): (Direction, u32)) -> Option<Point> {
+    let (dx, dy, dz) = direction.delta();
+    let end = |c: i32, d: i32| i32::try_from(c as i64 + d as i64 * count as i64).ok();
+    Some(Point {
+        x: end(p.x, dx)?,
+        y: end(p.y, dy)?,
+        z: end(p.z, dz)?,
+    })
+}
+
+/// Splits the input in wires, one per line
+pub fn split_input(s: &str) -> Vec<Wire> {
+    s.split("\n").map(Wire::from).collect()
+}
+
+/// Same as `split_input`, for input that may be malformed
+pub fn parse_input(s: &str) -> Result<Vec<Wire>, String> {
+    s.split("\n")
+        .enumerate()
+        .map(|(i, line)| Wire::parse(line).map_err(|e| format!("line {}: {}", i + 1, e)))
+        .collect()
 }
 
 fn crossing_distance(s: &str) -> u32 {
     let wires = split_input(s);
-    assert_eq!(wires.len(), 2);
-    let mut intersections = vec![];
-    for s1 in wires.get(0).unwrap().segments() {
-        for s2 in wires.get(1).unwrap().segments() {
-            intersections.extend(segments_intersect(&s1, &s2));
+    best_crossing(&wires, Criterion::Distance)
+        .unwrap()
+        .point
+        .distance_from_origin()
+}
+
+/// Steps taken along the wire before each segment starts
+fn steps_before(segments: &[Segment]) -> Vec<u32> {
+    segments
+        .iter()
+        .scan(0, |steps, s| {
+            let before = *steps;
+            *steps += s.len();
+            Some(before)
+        })
+        .collect()
+}
+
+/// Points shared by two wires with the steps each wire took to get there. A point is listed
+/// once per time the wires meet there.
+fn meeting_points(wire1: &Wire, wire2: &Wire) -> Vec<(Point, u32, u32)> {
+    let (segs1, segs2) = (wire1.segments(), wire2.segments());
+    let (steps1, steps2) = (steps_before(&segs1), steps_before(&segs2));
+    let mut points = vec![];
+    for (i, j) in sweep::touching_pairs(&segs1, &segs2) {
+        let (s1, s2) = (&segs1[i], &segs2[j]);
+        for p in segments_intersect(s1, s2) {
+            points.push((p, steps1[i] + s1.steps_to(&p), steps2[j] + s2.steps_to(&p)));
         }
     }
-    intersections
-        .into_iter()
-        .filter(|p| *p != Point { x: 0, y: 0 })
-        .map(|p| p.distance_from_origin())
-        .min()
-        .unwrap()
+    points
 }
 
 fn intersections_with_steps(wire1: &Wire, wire2: &Wire) -> Vec<PointWithSteps> {
+    meeting_points(wire1, wire2)
+        .into_iter()
+        .map(|(point, steps1, steps2)| PointWithSteps {
+            point,
+            steps: steps1 + steps2,
+        })
+        .collect()
+}
+
+/// A point where several wires cross.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Crossing {
+    pub point: Point,
+    /// Index of each wire going through the point, with the steps it takes to first get there
+    pub wires: Vec<(usize, u32)>,
+}
+
+impl Crossing {
+    /// Whether every wire involved starts there, which makes it no crossing at all
+    pub fn is_start(&self) -> bool {
+        self.wires.iter().all(|(_, steps)| *steps == 0)
+    }
+
+    /// Sum of the steps of every wire involved
+    pub fn combined_steps(&self) -> u32 {
+        self.wires.iter().map(|(_, steps)| steps).sum()
+    }
+}
+
+/// How to pick the best crossing
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Criterion {
+    /// Closest to the origin, in Manhattan distance, see `closest_crossing` for other choices
+    Distance,
+    /// Fewest combined steps
+    Steps,
+}
+
+/// Crossings of every pair of wires `(i, j)`, `i < j`, keeping the fewest steps for each point.
+pub fn pairwise_intersections(wires: &[Wire]) -> Vec<(usize, usize, PointWithSteps)> {
     let mut intersections = vec![];
-    let mut steps1 = 0;
-    for s1 in wire1.segments() {
-        let mut steps2 = 0;
-        for s2 in wire2.segments() {
-            let int = segments_intersect(&s1, &s2);
-            for i in int {
-                let p = PointWithSteps {
-                    point: i,
-                    steps: steps1 + steps2 + i.distance_from(&s1.a) + i.distance_from(&s2.a),
-                };
-                intersections.push(p);
+    for i in 0..wires.len() {
+        for j in i + 1..wires.len() {
+            let mut best: HashMap<Point, u32> = HashMap::new();
+            for p in intersections_with_steps(&wires[i], &wires[j]) {
+                let steps = best.entry(p.point).or_insert(p.steps);
+                *steps = min(*steps, p.steps);
             }
-            steps2 += s2.len();
+            let mut points: Vec<_> = best.into_iter().collect();
+            points.sort_by_key(|(p, _)| (p.x, p.y, p.z));
+            intersections.extend(
+                points
+                    .into_iter()
+                    .map(|(point, steps)| (i, j, PointWithSteps { point, steps })),
+            );
         }
-        steps1 += s1.len()
     }
     intersections
 }
 
+/// Every point where at least two wires meet, ordered by coordinates.
+pub fn crossings(wires: &[Wire]) -> Vec<Crossing> {
+    let mut visits = vec![];
+    for i in 0..wires.len() {
+        for j in i + 1..wires.len() {
+            for (p, steps1, steps2) in meeting_points(&wires[i], &wires[j]) {
+                visits.push((p, i, steps1));
+                visits.push((p, j, steps2));
+            }
+        }
+    }
+    gather_crossings(visits)
+}
+
+/// Turns visits `(point, wire, steps)` to shared points into crossings, ordered by coordinates
+/// and keeping the fewest steps of each wire.
+fn gather_crossings(visits: Vec<(Point, usize, u32)>) -> Vec<Crossing> {
+    let mut by_point: HashMap<Point, BTreeMap<usize, u32>> = HashMap::new();
+    for (p, w, steps) in visits {
+        let s = by_point.entry(p).or_default().entry(w).or_insert(steps);
+        *s = min(*s, steps);
+    }
+    let mut crossings: Vec<Crossing> = by_point
+        .into_iter()
+        .map(|(point, wires)| Crossing {
+            point,
+            wires: wires.into_iter().collect(),
+        })
+        .collect();
+    crossings.sort_by_key(|c| (c.point.x, c.point.y, c.point.z));
+    crossings
+}
+
+/// Points crossed by at least `k` of the wires
+pub fn crossed_by_at_least(wires: &[Wire], k: usize) -> Vec<Crossing> {
+    crossings(wires)
+        .into_iter()
+        .filter(|c| c.wires.len() >= k)
+        .collect()
+}
+
+/// Best crossing, leaving out the points where all the wires involved start.
+pub fn best_crossing(wires: &[Wire], criterion: Criterion) -> Option<Crossing> {
+    match criterion {
+        Criterion::Distance => closest_crossing(wires, &Point::ORIGIN, &Manhattan),
+        Criterion::Steps => crossings(wires)
+            .into_iter()
+            .filter(|c| !c.is_start())
+            .min_by_key(|c| c.combined_steps()),
+    }
+}
+
+/// Crossing closest to `reference` by the given metric, the first one by coordinates on ties.
+pub fn closest_crossing<M>(wires: &[Wire], reference: &Point, metric: &M) -> Option<Crossing>
+where
+    M: Metric + ?Sized,
+{
+    crossings(wires)
+        .into_iter()
+        .filter(|c| !c.is_start())
+        .min_by(|c1, c2| {
+            let d1 = metric.distance(&c1.point, reference);
+            d1.total_cmp(&metric.distance(&c2.point, reference))
+        })
+}
+
 fn best_intersection(s: &str) -> u32 {
     let wires = split_input(s);
-    assert_eq!(wires.len(), 2);
-    intersections_with_steps(&wires.get(0).unwrap(), &wires.get(1).unwrap())
-        .into_iter()
-        .filter(|p| p.point != Point { x: 0, y: 0 })
-        .map(|i| i.steps)
-        .min()
+    best_crossing(&wires, Criterion::Steps)
         .unwrap()
+        .combined_steps()
 }
 
 pub fn entry_a(wires: String) -> String {
@@ -223,11 +581,23 @@ pub fn entry_b(wires: String) -> String {
 
 #[cfg(test)]
 mod tests {
+    use super::generate::Rng;
     use super::*;
 
     #[test]
-    fn test_decode_step() {
-        assert_eq!(decode_step("D12"), (Direction::Down, 12))
+    fn test_parse_step() {
+        assert_eq!(parse_step("D12"), Ok((Direction::Down, 12)));
+        assert_eq!(parse_step("UR3"), Ok((Direction::UpRight, 3)));
+        assert_eq!(parse_step("DL10"), Ok((Direction::DownLeft, 10)));
+        assert_eq!(parse_step("V3:-2"), Ok((Direction::Vector(3, -2, 0), 1)));
+        assert_eq!(parse_step("V3:-2:1"), Ok((Direction::Vector(3, -2, 1), 1)));
+        assert_eq!(parse_step("F4"), Ok((Direction::Forward, 4)));
+        assert!(parse_step("R3000000000").is_err());
+
+        assert!(Wire::parse("R2147483647,L2147483647,L2147483647").is_ok());
+        assert!(Wire::parse("V2147483647:0,V2147483647:0").is_err());
+        assert!(Wire::parse("D2147483647,D2").is_err());
+        assert_eq!(Wire::parse("R8,X5"), Err(String::from("Bad move \"X5\"")));
     }
 
     #[test]
@@ -235,6 +605,7 @@ mod tests {
         assert_eq!(
             Wire::from("D12,U14,R1"),
             Wire {
+                start: Point::ORIGIN,
                 v: vec![
                     (Direction::Down, 12),
                     (Direction::Up, 14),
@@ -244,12 +615,23 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_parse_input() {
+        let input = "R8,U5,L5,D3\nU7,R6,D4,L4";
+        assert_eq!(parse_input(input), Ok(split_input(input)));
+        assert_eq!(
+            parse_input("R8,U5\nR8,X5"),
+            Err(String::from("line 2: Bad move \"X5\""))
+        );
+    }
+
     #[test]
     fn test_split_input() {
         assert_eq!(
             split_input("U32,U5,L2\nD12,U14,R1"),
             vec![
                 Wire {
+                    start: Point::ORIGIN,
                     v: vec![
                         (Direction::Up, 32),
                         (Direction::Up, 5),
@@ -257,6 +639,7 @@ mod tests {
                     ]
                 },
                 Wire {
+                    start: Point::ORIGIN,
                     v: vec![
                         (Direction::Down, 12),
                         (Direction::Up, 14),
@@ -272,6 +655,7 @@ D12,U14,R1"
             ),
             vec![
                 Wire {
+                    start: Point::ORIGIN,
                     v: vec![
                         (Direction::Up, 32),
                         (Direction::Up, 5),
@@ -279,6 +663,7 @@ D12,U14,R1"
                     ]
                 },
                 Wire {
+                    start: Point::ORIGIN,
                     v: vec![
                         (Direction::Down, 12),
                         (Direction::Up, 14),
@@ -292,6 +677,7 @@ D12,U14,R1"
     #[test]
     fn test_segments() {
         let w = Wire {
+            start: Point::ORIGIN,
             v: vec![
                 (Direction::Up, 32),
                 (Direction::Up, 5),
@@ -300,16 +686,16 @@ D12,U14,R1"
         };
         let expected = vec![
             Segment {
-                a: Point { x: 0, y: 0 },
-                b: Point { x: 0, y: 32 },
+                a: Point::new(0, 0),
+                b: Point::new(0, 32),
             },
             Segment {
-                a: Point { x: 0, y: 32 },
-                b: Point { x: 0, y: 37 },
+                a: Point::new(0, 32),
+                b: Point::new(0, 37),
             },
             Segment {
-                a: Point { x: 0, y: 37 },
-                b: Point { x: -2, y: 37 },
+                a: Point::new(0, 37),
+                b: Point::new(-2, 37),
             },
         ];
         assert_eq!(w.segments(), expected);
@@ -318,66 +704,332 @@ D12,U14,R1"
     #[test]
     fn test_intersect() {
         let seg1 = Segment {
-            a: Point { x: 0, y: 0 },
-            b: Point { x: 1, y: 0 },
+            a: Point::new(0, 0),
+            b: Point::new(1, 0),
         };
         let seg2 = Segment {
-            a: Point { x: 2, y: 0 },
-            b: Point { x: 2, y: 1 },
+            a: Point::new(2, 0),
+            b: Point::new(2, 1),
         };
         assert_eq!(segments_intersect(&seg1, &seg2), vec![]);
 
         let seg1 = Segment {
-            a: Point { x: -5, y: 0 },
-            b: Point { x: 5, y: 0 },
+            a: Point::new(-5, 0),
+            b: Point::new(5, 0),
         };
         let seg2 = Segment {
-            a: Point { x: 0, y: -5 },
-            b: Point { x: 0, y: 5 },
+            a: Point::new(0, -5),
+            b: Point::new(0, 5),
         };
-        assert_eq!(segments_intersect(&seg1, &seg2), vec![Point { x: 0, y: 0 }]);
+        assert_eq!(segments_intersect(&seg1, &seg2), vec![Point::new(0, 0)]);
     }
 
     #[test]
-    fn official_results_a() {
+    fn test_intersect_collinear() {
+        let seg1 = Segment {
+            a: Point::new(0, 3),
+            b: Point::new(5, 3),
+        };
+        let seg2 = Segment {
+            a: Point::new(8, 3),
+            b: Point::new(2, 3),
+        };
         assert_eq!(
-            crossing_distance(
-                "R75,D30,R83,U83,L12,D49,R71,U7,L72
-U62,R66,U55,R34,D71,R55,D58,R83"
-            ),
-            159
+            segments_intersect(&seg1, &seg2),
+            vec![
+                Point::new(2, 3),
+                Point::new(3, 3),
+                Point::new(4, 3),
+                Point::new(5, 3)
+            ]
         );
         assert_eq!(
-            crossing_distance(
-                "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51
-U98,R91,D20,R16,D67,R40,U7,R15,U6,R7"
-            ),
-            135
+            overlap(&seg1, &seg2),
+            Some(Segment {
+                a: Point::new(2, 3),
+                b: Point::new(5, 3),
+            })
         );
+
+        // Touching at an end
+        let seg2 = Segment {
+            a: Point::new(5, 3),
+            b: Point::new(5, 10),
+        };
+        assert_eq!(segments_intersect(&seg1, &seg2), vec![Point::new(5, 3)]);
+
+        // Parallel but not on the same line
+        let seg2 = Segment {
+            a: Point::new(0, 4),
+            b: Point::new(5, 4),
+        };
+        assert_eq!(segments_intersect(&seg1, &seg2), vec![]);
+        let seg1 = Segment {
+            a: Point::new(1, 0),
+            b: Point::new(1, 5),
+        };
+        let seg2 = Segment {
+            a: Point::new(2, 0),
+            b: Point::new(2, 5),
+        };
+        assert_eq!(overlap(&seg1, &seg2), None);
+    }
+
+    /// Segment along one of the 26 unit directions, half of them staying in the `z = 0` layer
+    fn random_segment(rng: &mut Rng) -> Segment {
+        let a = Point {
+            x: rng.range(-5, 5),
+            y: rng.range(-5, 5),
+            z: if rng.next_u64() & 1 == 0 {
+                0
+            } else {
+                rng.range(-2, 2)
+            },
+        };
+        let len = rng.range(-6, 6);
+        let (dx, dy) = (rng.range(-1, 1), rng.range(-1, 1));
+        let dz = if a.z == 0 { 0 } else { rng.range(-1, 1) };
+        let b = Point {
+            x: a.x + len * dx,
+            y: a.y + len * dy,
+            z: a.z + len * dz,
+        };
+        Segment { a, b }
+    }
+
+    /// Walks every unit step of a segment
+    fn walk(seg: &Segment) -> Vec<Point> {
+        let mut p = seg.a;
+        let mut points = vec![p];
+        while p != seg.b {
+            p.x += (seg.b.x - p.x).signum();
+            p.y += (seg.b.y - p.y).signum();
+            p.z += (seg.b.z - p.z).signum();
+            points.push(p);
+        }
+        points
     }
 
     #[test]
-    fn official_results_b() {
+    fn test_intersect_against_grid_walk() {
+        let mut rng = Rng::new(0x5eed);
+        for _ in 0..10_000 {
+            let seg1 = random_segment(&mut rng);
+            let seg2 = random_segment(&mut rng);
+            let other = walk(&seg2);
+            let mut expected: Vec<Point> = walk(&seg1)
+                .into_iter()
+                .filter(|p| other.contains(p))
+                .collect();
+            let mut found = segments_intersect(&seg1, &seg2);
+            expected.sort_by_key(|p| (p.x, p.y, p.z));
+            found.sort_by_key(|p| (p.x, p.y, p.z));
+            assert_eq!(
+                found,
+                expected,
+                "{:?} {:?}\n{}",
+                seg1,
+                seg2,
+                ascii::Diagram::default()
+                    .render_segments(&[vec![seg1.clone()], vec![seg2.clone()]])
+                    .unwrap()
+            );
+            assert_eq!(segments_intersect(&seg2, &seg1).len(), found.len());
+        }
+    }
+
+    #[test]
+    fn test_many_wires() {
+        let wires = split_input("R8,U5,L5,D3\nU7,R6,D4,L4\nU3,R10");
         assert_eq!(
-            best_intersection(
-                "R8,U5,L5,D3
-U7,R6,D4,L4"
-            ),
-            30
+            pairwise_intersections(&wires[..2]),
+            vec![
+                (
+                    0,
+                    1,
+                    PointWithSteps {
+                        point: Point::new(0, 0),
+                        steps: 0
+                    }
+                ),
+                (
+                    0,
+                    1,
+                    PointWithSteps {
+                        point: Point::new(3, 3),
+                        steps: 40
+                    }
+                ),
+                (
+                    0,
+                    1,
+                    PointWithSteps {
+                        point: Point::new(6, 5),
+                        steps: 30
+                    }
+                )
+            ]
         );
+        // The third wire runs along the second one from (0, 0) to (0, 3) and (2, 3) to (6, 3)
+        assert_eq!(pairwise_intersections(&wires).len(), 3 + 3 + 9);
+
+        // (3, 3) is on all three wires, the origin too
+        let all = crossed_by_at_least(&wires, 3);
+        assert_eq!(all.len(), 2);
         assert_eq!(
-            best_intersection(
-                "R75,D30,R83,U83,L12,D49,R71,U7,L72
-U62,R66,U55,R34,D71,R55,D58,R83"
-            ),
-            610
+            all[1],
+            Crossing {
+                point: Point::new(3, 3),
+                wires: vec![(0, 20), (1, 20), (2, 6)]
+            }
         );
+        assert_eq!(all[1].combined_steps(), 46);
+
+        let closest = best_crossing(&wires, Criterion::Distance).unwrap();
+        assert_eq!(closest.point, Point::new(0, 1));
+        let fastest = best_crossing(&wires, Criterion::Steps).unwrap();
+        assert_eq!(fastest.point, Point::new(0, 1));
+        assert_eq!(fastest.combined_steps(), 2);
         assert_eq!(
-            best_intersection(
-                "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51
-U98,R91,D20,R16,D67,R40,U7,R15,U6,R7"
-            ),
-            410
+            best_crossing(&wires[..2], Criterion::Steps).unwrap().point,
+            Point::new(6, 5)
+        );
+    }
+
+    #[test]
+    fn test_intersect_slanted() {
+        let seg = |ax, ay, bx, by| Segment {
+            a: Point::new(ax, ay),
+            b: Point::new(bx, by),
+        };
+        // Diagonals crossing between lattice points
+        assert_eq!(
+            segments_intersect(&seg(0, 0, 1, 1), &seg(0, 1, 1, 0)),
+            vec![]
+        );
+        assert_eq!(
+            segments_intersect(&seg(0, 0, 2, 2), &seg(0, 2, 2, 0)),
+            vec![Point::new(1, 1)]
+        );
+        // Vectors only have lattice points every (3, -2)
+        let v = seg(0, 0, 9, -6);
+        assert_eq!(v.len(), 3);
+        assert_eq!(
+            segments_intersect(&v, &seg(3, 5, 3, -5)),
+            vec![Point::new(3, -2)]
+        );
+        assert_eq!(segments_intersect(&v, &seg(4, 5, 4, -5)), vec![]);
+        assert_eq!(
+            segments_intersect(&v, &seg(12, -8, 3, -2)),
+            vec![Point::new(3, -2), Point::new(6, -4), Point::new(9, -6)]
+        );
+        assert_eq!(
+            segments_intersect(&v, &seg(6, -4, 6, -4)),
+            vec![Point::new(6, -4)]
+        );
+        assert_eq!(segments_intersect(&v, &seg(5, -4, 5, -4)), vec![]);
+    }
+
+    #[test]
+    fn test_diagonal_wires() {
+        let w = Wire::from("UR3,V4:-2,DL1");
+        assert_eq!(
+            w.segments().last(),
+            Some(&Segment {
+                a: Point::new(7, 1),
+                b: Point::new(6, 0),
+            })
+        );
+        assert_eq!(crossing_distance("UR4,R2\nR4,U10"), 8);
+        assert_eq!(best_intersection("UR4,R2\nR4,U10"), 4 + 8);
+        assert_eq!(best_intersection("V2:1,V2:1\nR4,U2"), 2 + 6);
+    }
+
+    #[test]
+    fn test_layers() {
+        // The second wire climbs a layer before going over the first one
+        assert_eq!(crossing_distance("F2,R8,U5\nU5,F2,R3,D10"), 3 + 2);
+        assert_eq!(best_intersection("F2,R8,U5\nU5,F2,R3,D10"), 5 + 15);
+        // Wires in different layers never meet, skew vectors neither
+        let wires = split_input("F1,R5\nU1,R5,D2");
+        assert_eq!(best_crossing(&wires, Criterion::Distance), None);
+        let skew = |a: (i32, i32, i32), b: (i32, i32, i32)| Segment {
+            a: Point {
+                x: a.0,
+                y: a.1,
+                z: a.2,
+            },
+            b: Point {
+                x: b.0,
+                y: b.1,
+                z: b.2,
+            },
+        };
+        assert_eq!(
+            overlap(&skew((0, 0, 0), (2, 2, 0)), &skew((0, 2, 1), (2, 0, 1))),
+            None
         );
+        // Slanted through the layers
+        let w1 = Wire::from("V2:2:2,V2:2:2");
+        assert_eq!(w1.segments()[1].len(), 2);
+        let w2 = Wire::from("R3,U3,F3,V-1:0:-1");
+        let found = best_crossing(&[w1, w2], Criterion::Steps).unwrap();
+        assert_eq!(found.point, Point { x: 3, y: 3, z: 3 });
+        assert_eq!(found.point.distance_from_origin(), 9);
+        assert_eq!(found.wires, vec![(0, 3), (1, 9)]);
+    }
+
+    #[test]
+    fn test_starts_and_metrics() {
+        use super::metric::{Chebyshev, Euclidean};
+
+        let mut wires = split_input("R8,U5,L5,D3\nU7,R6,D4,L4");
+        wires.push(Wire::from("U4").starting_at(Point::new(5, -2)));
+        let closest = |x, y, metric: &dyn Metric| {
+            let reference = Point::new(x, y);
+            let point = closest_crossing(&wires, &reference, metric).unwrap().point;
+            (point.x, point.y)
+        };
+        assert_eq!(closest(0, 0, &Manhattan), (5, 0));
+        assert_eq!(closest(0, 0, &Chebyshev), (3, 3));
+        assert_eq!(closest(0, 0, &Euclidean), (3, 3));
+        assert_eq!(closest(7, 6, &Manhattan), (6, 5));
+
+        // The start of one wire only is a crossing like any other
+        let wires = vec![
+            Wire::from("R8,U5,L5,D3"),
+            Wire::from("U7,R6,D4,L4").starting_at(Point::new(1, 0)),
+        ];
+        let crossing = best_crossing(&wires, Criterion::Steps).unwrap();
+        assert_eq!(crossing.point, Point::new(1, 0));
+        assert_eq!(crossing.wires, vec![(0, 1), (1, 0)]);
+        assert_eq!(best_crossing(&wires[1..], Criterion::Distance), None);
+    }
+
+    const EXAMPLE_1: &str = "R8,U5,L5,D3
+U7,R6,D4,L4";
+    const EXAMPLE_2: &str = "R75,D30,R83,U83,L12,D49,R71,U7,L72
+U62,R66,U55,R34,D71,R55,D58,R83";
+    const EXAMPLE_3: &str = "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51
+U98,R91,D20,R16,D67,R40,U7,R15,U6,R7";
+
+    /// The wires drawn small enough for a failing test's output
+    fn diagram(s: &str) -> String {
+        let wires = split_input(s);
+        ascii::Diagram::fit(&wires, 80, 40).render(&wires).unwrap()
+    }
+
+    #[test]
+    fn official_results_a() {
+        for (input, expected) in [(EXAMPLE_2, 159), (EXAMPLE_3, 135)] {
+            assert_eq!(crossing_distance(input), expected, "\n{}", diagram(input));
+        }
+    }
+
+    #[test]
+    fn official_results_b() {
+        for (input, expected) in [(EXAMPLE_1, 30), (EXAMPLE_2, 610), (EXAMPLE_3, 410)] {
+            assert_eq!(best_intersection(input), expected, "\n{}", diagram(input));
+        }
     }
 }
@@ -0,0 +1,213 @@
+//! Simplification of wires, and writing them back as text.
+//!
+//! Consecutive moves going the same way are merged and empty moves dropped, which keeps every
+//! point at the same number of steps. A move going back over the previous one is cancelled
+//! against it, that changes the steps taken to reach whatever comes after, so the moves
+//! involved are reported.
+
+use std::fmt;
+use std::fmt::{Error, Formatter};
+
+use super::{lattice_steps, Direction, Wire};
+
+/// A normalised wire
+#[derive(PartialEq, Debug)]
+pub struct Normalized {
+    pub wire: Wire,
+    /// Moves of the original wire cancelled against each other, in order
+    pub lossy: Vec<usize>,
+}
+
+impl Normalized {
+    /// Whether every point is still reached in the same number of steps
+    pub fn is_exact(&self) -> bool {
+        self.lossy.is_empty()
+    }
+}
+
+/// A move as a number of steps along a primitive direction
+struct Run {
+    unit: (i32, i32, i32),
+    steps: u32,
+    /// Moves of the original wire merged into this one
+    moves: Vec<usize>,
+}
+
+fn direction_of(unit: (i32, i32, i32), steps: u32) -> (Direction, u32) {
+    use Direction::*;
+    let named = [
+        Up, Down, Right, Left, UpRight, UpLeft, DownRight, DownLeft, Forward, Back,
+    ];
+    match named.iter().find(|d| d.delta() == unit) {
+        Some(d) => (*d, steps),
+        None => {
+            let n = steps as i32;
+            (Vector(unit.0 * n, unit.1 * n, unit.2 * n), 1)
+        }
+    }
+}
+
+impl Wire {
+    /// Merges collinear moves, removes empty ones and cancels backtracks.
+    pub fn normalize(&self) -> Normalized {
+        let mut runs: Vec<Run> = vec![];
+        let mut lossy = vec![];
+        for (i, (direction, count)) in self.v.iter().enumerate() {
+            let (dx, dy, dz) = direction.delta();
+            let n = *count as i32;
+            let delta = (dx * n, dy * n, dz * n);
+            let steps = lattice_steps(delta);
+            if steps == 0 {
+                continue;
+            }
+            let s = steps as i32;
+            let unit = (delta.0 / s, delta.1 / s, delta.2 / s);
+            let last = match runs.last_mut() {
+                Some(last) => last,
+                None => {
+                    runs.push(Run {
+                        unit,
+                        steps,
+                        moves: vec![i],
+                    });
+                    continue;
+                }
+            };
+            if last.unit == unit {
+                last.steps += steps;
+                last.moves.push(i);
+            } else if last.unit == (-unit.0, -unit.1, -unit.2) {
+                lossy.extend(last.moves.iter().copied());
+                lossy.push(i);
+                if last.steps > steps {
+                    last.steps -= steps;
+                } else if last.steps < steps {
+                    *last = Run {
+                        unit,
+                        steps: steps - last.steps,
+                        moves: vec![i],
+                    };
+                } else {
+                    runs.pop();
+                }
+            } else {
+                runs.push(Run {
+                    unit,
+                    steps,
+                    moves: vec![i],
+                });
+            }
+        }
+        lossy.sort_unstable();
+        lossy.dedup();
+        Normalized {
+            wire: Wire {
+                v: runs.iter().map(|r| direction_of(r.unit, r.steps)).collect(),
+                start: self.start,
+            },
+            lossy,
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        use Direction::*;
+        let code = match self {
+            Up => "U",
+            Down => "D",
+            Right => "R",
+            Left => "L",
+            UpRight => "UR",
+            UpLeft => "UL",
+            DownRight => "DR",
+            DownLeft => "DL",
+            Forward => "F",
+            Back => "B",
+            Vector(dx, dy, 0) => return write!(f, "V{}:{}", dx, dy),
+            Vector(dx, dy, dz) => return write!(f, "V{}:{}:{}", dx, dy, dz),
+        };
+        write!(f, "{}", code)
+    }
+}
+
+/// The moves in the input format, the start point is left out. A wire without any move is an
+/// empty string, which `Wire::from` reads back.
+impl fmt::Display for Wire {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        for (i, (direction, count)) in self.v.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            match direction {
+                Direction::Vector(dx, dy, dz) => {
+                    let n = *count as i32;
+                    write!(f, "{}", Direction::Vector(dx * n, dy * n, dz * n))?
+                }
+                _ => write!(f, "{}{}", direction, count)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::generate::{random_moves, Rng};
+    use super::*;
+
+    fn normalize(s: &str) -> (String, Vec<usize>) {
+        let n = Wire::from(s).normalize();
+        (n.wire.to_string(), n.lossy)
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("U32,U5,L2"), (String::from("U37,L2"), vec![]));
+        assert_eq!(normalize("R0,U3,U0,U2"), (String::from("U5"), vec![]));
+        assert_eq!(normalize("V2:2,UR1,V0:0"), (String::from("UR3"), vec![]));
+        assert_eq!(
+            normalize("V4:-6,V2:-3:1"),
+            (String::from("V4:-6,V2:-3:1"), vec![])
+        );
+        assert_eq!(normalize("U5,D3,L2"), (String::from("U2,L2"), vec![0, 1]));
+        assert_eq!(normalize("L1,U5,D5,R1"), (String::new(), vec![0, 1, 2, 3]));
+        assert_eq!(
+            normalize("R3,U2,D5,F1"),
+            (String::from("R3,D3,F1"), vec![1, 2])
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        for s in &["R75,D30,R83,U83,L12", "UR3,F2,B1,V3:-2,V1:1:-4", ""] {
+            assert_eq!(Wire::from(s).to_string(), *s);
+        }
+        let empty = Wire::from("U2,D2").normalize().wire;
+        assert_eq!(Wire::from(&empty.to_string()), empty);
+        assert_eq!(empty.segments(), vec![]);
+    }
+
+    #[test]
+    fn test_same_walk() {
+        let mut rng = Rng::new(0xfeed);
+        for _ in 0..300 {
+            let wire = Wire::from(random_moves(&mut rng, 8, 3, 6).join(",").as_str());
+            let normalized = wire.normalize();
+            let walk = |w: &Wire| -> Vec<_> {
+                let mut points = vec![w.start()];
+                for s in w.segments() {
+                    points.extend(s.points().into_iter().skip(1));
+                }
+                points
+            };
+            let (before, after) = (walk(&wire), walk(&normalized.wire));
+            if normalized.is_exact() {
+                assert_eq!(after, before, "{}", wire);
+            } else {
+                assert!(after.len() < before.len(), "{}", wire);
+            }
+            assert_eq!(after.last(), before.last(), "{}", wire);
+        }
+    }
+}
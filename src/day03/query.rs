@@ -0,0 +1,263 @@
+//! Step queries on a single wire: where it is after a number of steps, and how many steps it
+//! takes to first reach a point.
+//!
+//! Both are logarithmic in the number of segments, times the number of different directions
+//! the wire moves in for the latter.
+//!
+//! Steps taken before each segment are kept as prefix sums, so finding the segment holding a
+//! given step is a binary search. For the other way around, segments are grouped by the line
+//! they lie on. Along a line, the segment ends cut it into pieces covered by the same segments,
+//! and for each piece only two segments can be the first to get there: among the segments going
+//! one way, the cost of reaching any point of the piece differs by a constant. Finding the
+//! piece of a point is again a binary search.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use super::{cross, dot, steps_before, wide, Point, Segment, Wire};
+
+/// A line of the lattice: its primitive direction, made to point forward, and the cross
+/// product of that direction with any of its points.
+type Line = ((i32, i32, i32), (i128, i128, i128));
+
+/// The two segments that first reach a piece of a line, as indexes into the wire's segments
+#[derive(Debug, Default, Copy, Clone)]
+struct Best {
+    forward: Option<usize>,
+    backward: Option<usize>,
+}
+
+/// Segments along a line. Positions are `dot(point, direction)`, which grows by the same
+/// amount at every step along the line.
+#[derive(Debug)]
+struct LineIndex {
+    /// Sorted positions of the segment ends
+    ends: Vec<i128>,
+    /// Best segments for the end `i` at `2 * i`, for the gap after it at `2 * i + 1`
+    pieces: Vec<Best>,
+}
+
+/// Steps and positions along a wire, answered in logarithmic time.
+#[derive(Debug)]
+pub struct WireIndex {
+    segments: Vec<Segment>,
+    before: Vec<u32>,
+    lines: HashMap<Line, LineIndex>,
+    /// Directions of the lines, a point is looked up on the line of each of them
+    directions: Vec<(i32, i32, i32)>,
+    /// Empty moves, they do not lie on any line
+    stops: HashMap<Point, u32>,
+}
+
+fn forward((dx, dy, dz): (i32, i32, i32)) -> (i32, i32, i32) {
+    if (dx, dy, dz) < (0, 0, 0) {
+        (-dx, -dy, -dz)
+    } else {
+        (dx, dy, dz)
+    }
+}
+
+fn line_of(s: &Segment) -> Line {
+    let u = forward(s.unit());
+    (u, cross(wide((s.a.x, s.a.y, s.a.z)), wide(u)))
+}
+
+fn position(p: &Point, u: (i32, i32, i32)) -> i128 {
+    dot(wide((p.x, p.y, p.z)), wide(u))
+}
+
+impl WireIndex {
+    pub fn new(wire: &Wire) -> WireIndex {
+        let segments = wire.segments();
+        let before = steps_before(&segments);
+        let mut stops: HashMap<Point, u32> = HashMap::new();
+        let mut on_line: HashMap<Line, Vec<usize>> = HashMap::new();
+        for (i, s) in segments.iter().enumerate() {
+            if s.is_empty() {
+                stops.entry(s.a).or_insert(before[i]);
+            } else {
+                on_line.entry(line_of(s)).or_default().push(i);
+            }
+        }
+        let mut directions: Vec<_> = on_line.keys().map(|line| line.0).collect();
+        directions.sort_unstable();
+        directions.dedup();
+        let mut index = WireIndex {
+            segments,
+            before,
+            lines: HashMap::new(),
+            directions,
+            stops,
+        };
+        for (line, members) in on_line {
+            let line_index = index.index_line(line.0, &members);
+            index.lines.insert(line, line_index);
+        }
+        index
+    }
+
+    fn index_line(&self, u: (i32, i32, i32), members: &[usize]) -> LineIndex {
+        let span = |i: usize| {
+            let s = &self.segments[i];
+            let (a, b) = (position(&s.a, u), position(&s.b, u));
+            (a.min(b), a.max(b))
+        };
+        let mut ends: Vec<i128> = members
+            .iter()
+            .flat_map(|&i| [span(i).0, span(i).1])
+            .collect();
+        ends.sort_unstable();
+        ends.dedup();
+        let mut pieces = vec![Best::default(); 2 * ends.len()];
+        for going_forward in [true, false] {
+            // Costs of parallel segments going the same way differ by a constant, `key ± t`
+            // at position `t`: the segment with the lowest key is the best along its span.
+            // Ties go to the first member.
+            let mut spans: Vec<(usize, usize, i128, usize)> = members
+                .iter()
+                .enumerate()
+                .filter(|(_, &i)| {
+                    (forward(self.segments[i].unit()) == self.segments[i].unit()) == going_forward
+                })
+                .map(|(order, &i)| {
+                    let (lo, hi) = span(i);
+                    let key = if going_forward {
+                        self.cost(i, hi, u) - hi
+                    } else {
+                        self.cost(i, lo, u) + lo
+                    };
+                    let first = 2 * ends.binary_search(&lo).unwrap();
+                    let last = 2 * ends.binary_search(&hi).unwrap();
+                    (first, last, key, order)
+                })
+                .collect();
+            spans.sort_unstable();
+            let mut spans = spans.into_iter().peekable();
+            let mut active = BinaryHeap::new();
+            for (k, piece) in pieces.iter_mut().enumerate() {
+                while let Some((_, last, key, order)) = spans.next_if(|span| span.0 <= k) {
+                    active.push(Reverse((key, order, last)));
+                }
+                while active.peek().is_some_and(|Reverse((_, _, last))| *last < k) {
+                    active.pop();
+                }
+                if let Some(Reverse((_, order, _))) = active.peek() {
+                    let best = if going_forward {
+                        &mut piece.forward
+                    } else {
+                        &mut piece.backward
+                    };
+                    *best = Some(members[*order]);
+                }
+            }
+        }
+        LineIndex { ends, pieces }
+    }
+
+    /// Steps for segment `i` to get to position `t` of the segment, scaled by `dot(u, u)`
+    fn cost(&self, i: usize, t: i128, u: (i32, i32, i32)) -> i128 {
+        let s = &self.segments[i];
+        let uu = dot(wide(u), wide(u));
+        self.before[i] as i128 * uu + (t - position(&s.a, u)).abs()
+    }
+
+    /// Steps taken along the whole wire
+    pub fn total_steps(&self) -> u32 {
+        match (self.segments.last(), self.before.last()) {
+            (Some(s), Some(before)) => before + s.len(),
+            _ => 0,
+        }
+    }
+
+    /// Where the wire is after `n` steps, `None` once it has ended.
+    pub fn point_at_step(&self, n: u32) -> Option<Point> {
+        if n > self.total_steps() {
+            return None;
+        }
+        let i = self.before.partition_point(|&b| b <= n) - 1;
+        Some(self.segments[i].at((n - self.before[i]) as i32))
+    }
+
+    /// Steps the wire takes to first reach `p`, `None` if it never does.
+    pub fn steps_to_point(&self, p: &Point) -> Option<u32> {
+        let mut best = self.stops.get(p).copied();
+        for &u in &self.directions {
+            let line = (u, cross(wide((p.x, p.y, p.z)), wide(u)));
+            let index = match self.lines.get(&line) {
+                Some(index) => index,
+                None => continue,
+            };
+            let t = position(p, u);
+            let piece = match index.ends.binary_search(&t) {
+                Ok(i) => 2 * i,
+                Err(0) => continue,
+                Err(i) if i == index.ends.len() => continue,
+                Err(i) => 2 * i - 1,
+            };
+            let Best { forward, backward } = index.pieces[piece];
+            for i in forward.into_iter().chain(backward) {
+                let steps = self.before[i] + self.segments[i].steps_to(p);
+                best = Some(best.map_or(steps, |b| b.min(steps)));
+            }
+        }
+        best
+    }
+}
+
+impl Wire {
+    pub fn index(&self) -> WireIndex {
+        WireIndex::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::generate::{random_moves, Rng};
+    use super::*;
+
+    #[test]
+    fn test_queries() {
+        let index = Wire::from("R8,U5,L5,D3").index();
+        assert_eq!(index.total_steps(), 21);
+        assert_eq!(index.point_at_step(0), Some(Point::new(0, 0)));
+        assert_eq!(index.point_at_step(10), Some(Point::new(8, 2)));
+        assert_eq!(index.point_at_step(21), Some(Point::new(3, 2)));
+        assert_eq!(index.point_at_step(22), None);
+        assert_eq!(index.steps_to_point(&Point::new(3, 3)), Some(20));
+        assert_eq!(index.steps_to_point(&Point::new(8, 5)), Some(13));
+        assert_eq!(index.steps_to_point(&Point::new(3, 1)), None);
+        assert_eq!(index.steps_to_point(&Point::new(9, 0)), None);
+
+        // Going back over itself, the first visit counts
+        let index = Wire::from("R10,L6,U0,R3,DR2").index();
+        assert_eq!(index.steps_to_point(&Point::new(5, 0)), Some(5));
+        assert_eq!(index.steps_to_point(&Point::new(8, 0)), Some(8));
+        assert_eq!(index.steps_to_point(&Point::new(8, -1)), Some(20));
+        assert_eq!(index.point_at_step(16), Some(Point::new(4, 0)));
+    }
+
+    #[test]
+    fn test_against_walk() {
+        let mut rng = Rng::new(0xdec0de);
+        for _ in 0..200 {
+            let count = rng.range(1, 12) as usize;
+            let steps = random_moves(&mut rng, count, 5, 8);
+            let wire = Wire::from(steps.join(",").as_str());
+            let index = wire.index();
+            let mut walked = vec![Point::new(0, 0)];
+            for s in wire.segments() {
+                walked.extend(s.points().into_iter().skip(1));
+            }
+            assert_eq!(index.total_steps() as usize, walked.len() - 1);
+            for (n, p) in walked.iter().enumerate() {
+                assert_eq!(index.point_at_step(n as u32), Some(*p), "{:?}", steps);
+                let first = walked.iter().position(|q| q == p).unwrap() as u32;
+                assert_eq!(index.steps_to_point(p), Some(first), "{:?} {:?}", steps, p);
+            }
+            let elsewhere = Point { x: 6, y: -6, z: 1 };
+            if !walked.contains(&elsewhere) {
+                assert_eq!(index.steps_to_point(&elsewhere), None);
+            }
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! Every crossing of the wires with what is known about it, as CSV or JSON.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use super::{overlap, steps_before, sweep, Point, Wire};
+
+/// How the wires meet at a crossing
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone)]
+pub enum Kind {
+    /// One of the segments starts or ends there
+    Touch,
+    /// The segments go through each other
+    Cross,
+    /// The segments run along each other
+    Overlap,
+}
+
+impl Kind {
+    fn name(&self) -> &'static str {
+        match self {
+            Kind::Touch => "touch",
+            Kind::Cross => "cross",
+            Kind::Overlap => "overlap",
+        }
+    }
+}
+
+/// A wire going through a crossing, the first time it gets there
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Visit {
+    pub wire: usize,
+    pub steps: u32,
+    /// Index of the segment of the wire
+    pub segment: usize,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Row {
+    pub point: Point,
+    /// Manhattan distance from the origin
+    pub distance: u32,
+    pub combined_steps: u32,
+    pub kind: Kind,
+    /// Ordered by wire
+    pub visits: Vec<Visit>,
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum SortBy {
+    Distance,
+    Steps,
+    /// By coordinates
+    Position,
+}
+
+impl SortBy {
+    pub fn from(s: &str) -> Option<SortBy> {
+        match s {
+            "distance" => Some(SortBy::Distance),
+            "steps" => Some(SortBy::Steps),
+            "position" => Some(SortBy::Position),
+            _ => None,
+        }
+    }
+}
+
+/// The crossings of a set of wires, leaving out the points where all the wires involved start
+#[derive(PartialEq, Debug, Clone)]
+pub struct Report {
+    pub rows: Vec<Row>,
+}
+
+impl Report {
+    /// All the crossings, sorted by coordinates
+    pub fn new(wires: &[Wire]) -> Report {
+        let segments: Vec<_> = wires.iter().map(Wire::segments).collect();
+        let before: Vec<_> = segments.iter().map(|s| steps_before(s)).collect();
+        let mut visits: HashMap<Point, BTreeMap<usize, Visit>> = HashMap::new();
+        let mut kinds: HashMap<Point, Kind> = HashMap::new();
+        for w1 in 0..wires.len() {
+            for w2 in w1 + 1..wires.len() {
+                let (segs1, segs2) = (&segments[w1], &segments[w2]);
+                for (i, j) in sweep::touching_pairs(segs1, segs2) {
+                    let (s1, s2) = (&segs1[i], &segs2[j]);
+                    let shared = overlap(s1, s2).unwrap();
+                    for p in shared.points() {
+                        let kind = if !shared.is_empty() {
+                            Kind::Overlap
+                        } else if [s1.a, s1.b, s2.a, s2.b].contains(&p) {
+                            Kind::Touch
+                        } else {
+                            Kind::Cross
+                        };
+                        let k = kinds.entry(p).or_insert(kind);
+                        *k = (*k).max(kind);
+                        for (wire, segment, s) in [(w1, i, s1), (w2, j, s2)] {
+                            let steps = before[wire][segment] + s.steps_to(&p);
+                            let visit = Visit {
+                                wire,
+                                steps,
+                                segment,
+                            };
+                            let v = visits.entry(p).or_default().entry(wire).or_insert(visit);
+                            if steps < v.steps {
+                                *v = visit;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        let mut rows: Vec<Row> = visits
+            .into_iter()
+            .filter(|(_, visits)| visits.values().any(|v| v.steps > 0))
+            .map(|(point, visits)| Row {
+                point,
+                distance: point.distance_from_origin(),
+                combined_steps: visits.values().map(|v| v.steps).sum(),
+                kind: kinds[&point],
+                visits: visits.into_values().collect(),
+            })
+            .collect();
+        rows.sort_by_key(|r| (r.point.x, r.point.y, r.point.z));
+        Report { rows }
+    }
+
+    /// Sorts the rows, by coordinates on ties
+    pub fn sort_by(&mut self, key: SortBy) {
+        self.rows.sort_by_key(|r| {
+            let first = match key {
+                SortBy::Distance => r.distance,
+                SortBy::Steps => r.combined_steps,
+                SortBy::Position => 0,
+            };
+            (first, r.point.x, r.point.y, r.point.z)
+        });
+    }
+
+    /// One line per crossing, the visits written as `wire=steps@segment` separated by `;`
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("x,y,z,distance,combined_steps,kind,visits\n");
+        for r in &self.rows {
+            let visits: Vec<String> = r
+                .visits
+                .iter()
+                .map(|v| format!("{}={}@{}", v.wire, v.steps, v.segment))
+                .collect();
+            writeln!(
+                csv,
+                "{},{},{},{},{},{},{}",
+                r.point.x,
+                r.point.y,
+                r.point.z,
+                r.distance,
+                r.combined_steps,
+                r.kind.name(),
+                visits.join(";")
+            )
+            .unwrap();
+        }
+        csv
+    }
+
+    /// An array with an object per crossing
+    pub fn to_json(&self) -> String {
+        let rows: Vec<String> = self
+            .rows
+            .iter()
+            .map(|r| {
+                let visits: Vec<String> = r
+                    .visits
+                    .iter()
+                    .map(|v| {
+                        format!(
+                            r#"{{"wire":{},"steps":{},"segment":{}}}"#,
+                            v.wire, v.steps, v.segment
+                        )
+                    })
+                    .collect();
+                format!(
+                    r#"{{"x":{},"y":{},"z":{},"distance":{},"combined_steps":{},"kind":"{}","visits":[{}]}}"#,
+                    r.point.x,
+                    r.point.y,
+                    r.point.z,
+                    r.distance,
+                    r.combined_steps,
+                    r.kind.name(),
+                    visits.join(",")
+                )
+            })
+            .collect();
+        format!("[{}]\n", rows.join(",\n "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{crossings, split_input};
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let wires = split_input("R8,U5,L5,D3\nU7,R6,D4,L4\nU3,R10");
+        let mut report = Report::new(&wires);
+        let expected: Vec<_> = crossings(&wires)
+            .into_iter()
+            .filter(|c| !c.is_start())
+            .collect();
+        assert_eq!(report.rows.len(), expected.len());
+        for (row, c) in report.rows.iter().zip(&expected) {
+            assert_eq!(row.point, c.point);
+            assert_eq!(row.combined_steps, c.combined_steps());
+            let steps: Vec<_> = row.visits.iter().map(|v| (v.wire, v.steps)).collect();
+            assert_eq!(steps, c.wires);
+        }
+
+        report.sort_by(SortBy::Steps);
+        let best = &report.rows[0];
+        assert_eq!(best.point, Point::new(0, 1));
+        assert_eq!(best.kind, Kind::Overlap);
+        report.sort_by(SortBy::Distance);
+        let corner = report
+            .rows
+            .iter()
+            .find(|r| r.point.x == 6 && r.point.y == 5);
+        assert_eq!(corner.unwrap().kind, Kind::Cross);
+        let three = report.rows.iter().find(|r| r.visits.len() == 3).unwrap();
+        assert_eq!(three.point, Point::new(3, 3));
+        assert_eq!(
+            three.visits,
+            vec![
+                Visit {
+                    wire: 0,
+                    steps: 20,
+                    segment: 3
+                },
+                Visit {
+                    wire: 1,
+                    steps: 20,
+                    segment: 3
+                },
+                Visit {
+                    wire: 2,
+                    steps: 6,
+                    segment: 1
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_export() {
+        let mut report = Report::new(&split_input("R8,U5,L5,D3\nU7,R6,D4,L4"));
+        report.sort_by(SortBy::Steps);
+        assert_eq!(
+            report.to_csv(),
+            "x,y,z,distance,combined_steps,kind,visits
+6,5,0,11,30,cross,0=15@2;1=15@2
+3,3,0,6,40,cross,0=20@3;1=20@3
+"
+        );
+        assert_eq!(
+            report.to_json(),
+            r#"[{"x":6,"y":5,"z":0,"distance":11,"combined_steps":30,"kind":"cross","visits":[{"wire":0,"steps":15,"segment":2},{"wire":1,"steps":15,"segment":2}]},
+ {"x":3,"y":3,"z":0,"distance":6,"combined_steps":40,"kind":"cross","visits":[{"wire":0,"steps":20,"segment":3},{"wire":1,"steps":20,"segment":3}]}]
+"#
+        );
+        assert_eq!(Report { rows: vec![] }.to_json(), "[]\n");
+    }
+}
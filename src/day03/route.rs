@@ -0,0 +1,234 @@
+//! Planning a new wire between two points around the existing ones.
+//!
+//! Dijkstra over the grid points of the layer, within the bounding box of the wires and the
+//! two ends grown by a margin. A route costs first the points it shares with existing wires,
+//! then its length, then its turns so it comes out in as few moves as possible. Only the points
+//! the search reaches are stored, the box can be as large as the puzzle input's.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use super::grid::walk;
+use super::{Direction, Point, Wire};
+
+/// Settings of the search
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Router {
+    /// Room left around the wires and the ends to go round them
+    pub margin: i32,
+    /// Largest number of grid points to reach before giving up
+    pub max_cells: usize,
+}
+
+impl Default for Router {
+    fn default() -> Router {
+        Router {
+            margin: 1,
+            max_cells: 4_000_000,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Route {
+    /// Starts at the first end, prints as the moves
+    pub wire: Wire,
+    /// Points shared with the existing wires, the start left aside
+    pub crossings: u32,
+    pub length: u32,
+}
+
+const MOVES: [Direction; 4] = [
+    Direction::Right,
+    Direction::Up,
+    Direction::Left,
+    Direction::Down,
+];
+
+/// Crossings, length then turns
+type Cost = (u32, u32, u32);
+
+/// A point and the index in `MOVES` of the move that got the route there
+type State = ((i32, i32), usize);
+
+impl Router {
+    /// Shortest route from `from` to `to` with the fewest crossings, moving along the grid.
+    /// Routing a point to itself gives a wire without any move.
+    pub fn route(&self, existing: &[Wire], from: Point, to: Point) -> Result<Route, String> {
+        if from.z != to.z {
+            return Err(format!("{:?} and {:?} are in different layers", from, to));
+        }
+        if from == to {
+            return Ok(Route {
+                wire: Wire {
+                    v: vec![],
+                    start: from,
+                },
+                crossings: 0,
+                length: 0,
+            });
+        }
+        let taken: HashSet<(i32, i32)> = existing
+            .iter()
+            .flat_map(|w| walk(w).into_keys())
+            .filter(|p| p.z == from.z)
+            .map(|p| (p.x, p.y))
+            .collect();
+        let ends = [(from.x, from.y), (to.x, to.y)];
+        let all = || taken.iter().chain(&ends);
+        let (x0, x1) = (all().map(|p| p.0).min(), all().map(|p| p.0).max());
+        let (y0, y1) = (all().map(|p| p.1).min(), all().map(|p| p.1).max());
+        let (x0, y0) = (x0.unwrap() - self.margin, y0.unwrap() - self.margin);
+        let (x1, y1) = (x1.unwrap() + self.margin, y1.unwrap() + self.margin);
+
+        let mut costs: HashMap<State, Cost> = HashMap::new();
+        let mut previous: HashMap<State, State> = HashMap::new();
+        let mut reached = HashSet::new();
+        let mut queue = BinaryHeap::new();
+        // The first move is no turn whichever way it goes
+        for d in 0..4 {
+            let s = ((from.x, from.y), d);
+            costs.insert(s, (0, 0, 0));
+            queue.push(Reverse(((0, 0, 0), s)));
+        }
+        let mut end = None;
+        while let Some(Reverse((cost, s))) = queue.pop() {
+            if costs[&s] != cost {
+                continue;
+            }
+            let ((x, y), direction) = s;
+            if (x, y) == (to.x, to.y) {
+                end = Some((cost, s));
+                break;
+            }
+            reached.insert((x, y));
+            if reached.len() > self.max_cells {
+                return Err(format!(
+                    "No route found within {} points, the limit",
+                    self.max_cells
+                ));
+            }
+            for (d, m) in MOVES.iter().enumerate() {
+                let (dx, dy, _) = m.delta();
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < x0 || nx > x1 || ny < y0 || ny > y1 {
+                    continue;
+                }
+                let next = (
+                    cost.0 + taken.contains(&(nx, ny)) as u32,
+                    cost.1 + 1,
+                    cost.2 + (d != direction) as u32,
+                );
+                let n = ((nx, ny), d);
+                if costs.get(&n).is_some_and(|c| *c <= next) {
+                    continue;
+                }
+                costs.insert(n, next);
+                previous.insert(n, s);
+                queue.push(Reverse((next, n)));
+            }
+        }
+        let ((crossings, length, _), mut s) = end.ok_or("No route")?;
+
+        let mut directions = vec![];
+        while let Some(&p) = previous.get(&s) {
+            directions.push(MOVES[s.1]);
+            s = p;
+        }
+        directions.reverse();
+        let mut v: Vec<(Direction, u32)> = vec![];
+        for d in directions {
+            match v.last_mut() {
+                Some((last, n)) if *last == d => *n += 1,
+                _ => v.push((d, 1)),
+            }
+        }
+        Ok(Route {
+            wire: Wire { v, start: from },
+            crossings,
+            length,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::generate::{Generator, Rng};
+    use super::super::split_input;
+    use super::*;
+
+    fn at(x: i32, y: i32) -> Point {
+        Point::new(x, y)
+    }
+
+    /// Checks the route goes from `from` to `to` as claimed, returns its moves
+    fn check(existing: &[Wire], route: &Route, to: Point) -> String {
+        let segments = route.wire.segments();
+        assert_eq!(segments.last().map_or(route.wire.start(), |s| s.b), to);
+        assert_eq!(segments.iter().map(|s| s.len()).sum::<u32>(), route.length);
+        let taken: HashSet<Point> = existing.iter().flat_map(|w| walk(w).into_keys()).collect();
+        let shared = walk(&route.wire)
+            .into_keys()
+            .filter(|p| *p != route.wire.start() && taken.contains(p))
+            .count();
+        assert_eq!(shared as u32, route.crossings);
+        route.wire.to_string()
+    }
+
+    #[test]
+    fn test_route() {
+        let router = Router::default();
+        let free = router.route(&[], at(0, 0), at(3, -2)).unwrap();
+        assert_eq!((free.length, free.crossings), (5, 0));
+        assert_eq!(check(&[], &free, at(3, -2)).split(',').count(), 2);
+
+        // A wall from (2, -3) to (2, 3), gone round above or below
+        let wall = [Wire::from("U6").starting_at(at(2, -3))];
+        let around = router.route(&wall, at(0, 0), at(4, 0)).unwrap();
+        assert_eq!((around.length, around.crossings), (12, 0));
+        assert_eq!(check(&wall, &around, at(4, 0)).split(',').count(), 3);
+        let tight = Router {
+            margin: 0,
+            ..Default::default()
+        };
+        let through = tight.route(&wall, at(0, 0), at(4, 0)).unwrap();
+        assert_eq!((through.length, through.crossings), (4, 1));
+        assert_eq!(check(&wall, &through, at(4, 0)), "R4");
+
+        let stay = router.route(&wall, at(1, 1), at(1, 1)).unwrap();
+        assert_eq!((stay.length, stay.crossings), (0, 0));
+        assert_eq!(stay.wire.start(), at(1, 1));
+        assert_eq!(Wire::from(&stay.wire.to_string()).segments(), vec![]);
+    }
+
+    #[test]
+    fn test_large_bounds() {
+        // Wires spread over 20001x20001 points, only the ones around the ends get searched
+        let (first, second) = Generator::default().pair(&mut Rng::new(3)).unwrap();
+        let wires = [first, second];
+        let route = Router::default()
+            .route(&wires, at(0, 0), at(30, -20))
+            .unwrap();
+        assert!(route.length >= 50);
+        check(&wires, &route, at(30, -20));
+    }
+
+    #[test]
+    fn test_enclosed() {
+        // The end lies on the second wire, walled in by both: one crossing to get in, one there
+        let wires = split_input("R8,U5,L5,D3\nU7,R6,D4,L4");
+        let route = Router::default()
+            .route(&wires, at(-2, 2), at(4, 3))
+            .unwrap();
+        assert_eq!(route.crossings, 2);
+        check(&wires, &route, at(4, 3));
+
+        let small = Router {
+            max_cells: 50,
+            ..Default::default()
+        };
+        assert!(small.route(&wires, at(-2, 2), at(4, 3)).is_err());
+        let up = Point { x: 4, y: 3, z: 1 };
+        assert!(Router::default().route(&wires, at(0, 0), up).is_err());
+    }
+}
@@ -0,0 +1,319 @@
+//! Spatial index over the segments of many wires, to find what goes through an area.
+//!
+//! The plane is cut into square cells and every segment is listed in the cells its lattice
+//! points fall in, whatever their layer. Queries only look at the cells they cover, and two
+//! segments can only meet if they share a cell.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{gather_crossings, overlap, segments_intersect, steps_before, Crossing, Point};
+use super::{Rect, Segment, Wire};
+
+/// A segment of one of the wires, `(wire, segment)`
+pub type SegmentId = (usize, usize);
+
+/// The closest point of a wire to another point
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Nearest {
+    pub segment: SegmentId,
+    pub point: Point,
+    pub distance: u32,
+}
+
+#[derive(Debug)]
+pub struct SegmentIndex {
+    cell: i32,
+    wires: Vec<Vec<Segment>>,
+    buckets: HashMap<(i32, i32), Vec<SegmentId>>,
+    /// Inclusive bounds of the cells holding segments
+    cells: Rect,
+}
+
+/// Steps `k` in `0..=n` such that `lo <= a + k * u <= hi`
+fn steps_within(a: i32, u: i32, n: i32, lo: i32, hi: i32) -> Option<(i32, i32)> {
+    if u == 0 {
+        return if lo <= a && a <= hi {
+            Some((0, n))
+        } else {
+            None
+        };
+    }
+    let (from, to) = ((lo - a) as f64 / u as f64, (hi - a) as f64 / u as f64);
+    let (k0, k1) = (from.min(to).ceil() as i32, from.max(to).floor() as i32);
+    let (k0, k1) = (k0.max(0), k1.min(n));
+    if k0 <= k1 {
+        Some((k0, k1))
+    } else {
+        None
+    }
+}
+
+/// Whether a lattice point of the segment lies within the rectangle
+fn crosses_rect(s: &Segment, ((x0, y0), (x1, y1)): Rect) -> bool {
+    let (ux, uy, _) = s.unit();
+    let n = s.len() as i32;
+    match (
+        steps_within(s.a.x, ux, n, x0, x1),
+        steps_within(s.a.y, uy, n, y0, y1),
+    ) {
+        (Some((a0, a1)), Some((b0, b1))) => a0.max(b0) <= a1.min(b1),
+        _ => false,
+    }
+}
+
+/// Closest lattice point of the segment to `p`, in Manhattan distance. The distance is convex
+/// along the segment so the best step is an end or next to where a coordinate lines up.
+fn nearest_on(s: &Segment, p: &Point) -> (Point, u32) {
+    let n = s.len() as i32;
+    let (ux, uy, uz) = s.unit();
+    let mut candidates = vec![0, n];
+    for (a, u, target) in [(s.a.x, ux, p.x), (s.a.y, uy, p.y), (s.a.z, uz, p.z)] {
+        if u != 0 {
+            let k = (target - a) as f64 / u as f64;
+            candidates.push(k.floor() as i32);
+            candidates.push(k.ceil() as i32);
+        }
+    }
+    candidates
+        .into_iter()
+        .map(|k| s.at(k.clamp(0, n)))
+        .map(|q| (q, q.distance_from(p)))
+        .min_by_key(|&(_, d)| d)
+        .unwrap()
+}
+
+/// Cells at Chebyshev distance `r` of `(cx, cy)` that lie `within` the bounds: the top and
+/// bottom rows, then the two side columns between them.
+fn ring((cx, cy): (i32, i32), r: i32, within: Rect) -> impl Iterator<Item = (i32, i32)> {
+    let ((x0, y0), (x1, y1)) = within;
+    let mut rows = vec![cy - r, cy + r];
+    let mut columns = vec![cx - r, cx + r];
+    rows.dedup();
+    columns.dedup();
+    rows.retain(|y| (y0..=y1).contains(y));
+    columns.retain(|x| (x0..=x1).contains(x));
+    let (left, right) = ((cx - r).max(x0), (cx + r).min(x1));
+    let (bottom, top) = ((cy - r + 1).max(y0), (cy + r - 1).min(y1));
+    let horizontal = rows
+        .into_iter()
+        .flat_map(move |y| (left..=right).map(move |x| (x, y)));
+    let vertical = columns
+        .into_iter()
+        .flat_map(move |x| (bottom..=top).map(move |y| (x, y)));
+    horizontal.chain(vertical)
+}
+
+impl SegmentIndex {
+    /// Indexes the wires with cells `cell` wide, a few times the typical segment length is a
+    /// good start.
+    pub fn new(wires: &[Wire], cell: i32) -> SegmentIndex {
+        SegmentIndex::from_segments(wires.iter().map(Wire::segments).collect(), cell)
+    }
+
+    pub fn from_segments(wires: Vec<Vec<Segment>>, cell: i32) -> SegmentIndex {
+        let cell = cell.max(1);
+        let mut buckets: HashMap<(i32, i32), Vec<SegmentId>> = HashMap::new();
+        for (w, segments) in wires.iter().enumerate() {
+            for (i, s) in segments.iter().enumerate() {
+                let mut last = None;
+                for p in s.points() {
+                    let c = (p.x.div_euclid(cell), p.y.div_euclid(cell));
+                    if last != Some(c) {
+                        let bucket = buckets.entry(c).or_default();
+                        if bucket.last() != Some(&(w, i)) {
+                            bucket.push((w, i));
+                        }
+                        last = Some(c);
+                    }
+                }
+            }
+        }
+        let xs = buckets.keys().map(|c| c.0);
+        let ys = buckets.keys().map(|c| c.1);
+        let cells = (
+            (xs.clone().min().unwrap_or(0), ys.clone().min().unwrap_or(0)),
+            (xs.max().unwrap_or(0), ys.max().unwrap_or(0)),
+        );
+        SegmentIndex {
+            cell,
+            wires,
+            buckets,
+            cells,
+        }
+    }
+
+    pub fn segment(&self, (w, i): SegmentId) -> &Segment {
+        &self.wires[w][i]
+    }
+
+    /// Segments listed in the cells of a rectangle, each once
+    fn candidates(&self, ((x0, y0), (x1, y1)): Rect) -> Vec<SegmentId> {
+        let mut found = HashSet::new();
+        for cx in x0.div_euclid(self.cell)..=x1.div_euclid(self.cell) {
+            for cy in y0.div_euclid(self.cell)..=y1.div_euclid(self.cell) {
+                if let Some(bucket) = self.buckets.get(&(cx, cy)) {
+                    found.extend(bucket);
+                }
+            }
+        }
+        let mut found: Vec<SegmentId> = found.into_iter().collect();
+        found.sort_unstable();
+        found
+    }
+
+    /// Segments with a lattice point within the rectangle, ordered by wire then segment.
+    pub fn in_rect(&self, rect: Rect) -> Vec<SegmentId> {
+        let ((x0, y0), (x1, y1)) = rect;
+        let rect = ((x0.min(x1), y0.min(y1)), (x0.max(x1), y0.max(y1)));
+        self.candidates(rect)
+            .into_iter()
+            .filter(|id| crosses_rect(self.segment(*id), rect))
+            .collect()
+    }
+
+    /// Segments going through a point, ordered by wire then segment.
+    pub fn at_point(&self, p: &Point) -> Vec<SegmentId> {
+        let here = Segment { a: *p, b: *p };
+        self.candidates(((p.x, p.y), (p.x, p.y)))
+            .into_iter()
+            .filter(|id| overlap(self.segment(*id), &here).is_some())
+            .collect()
+    }
+
+    /// The closest point of any wire, in Manhattan distance. Cells are searched in growing
+    /// rings around the point until no unsearched cell can hold anything closer. Only the part
+    /// of each ring overlapping the cells holding segments is searched, from the first ring
+    /// that reaches them.
+    pub fn nearest(&self, p: &Point) -> Option<Nearest> {
+        let (px, py) = (p.x.div_euclid(self.cell), p.y.div_euclid(self.cell));
+        let ((x0, y0), (x1, y1)) = self.cells;
+        let gap = |p: i32, lo: i32, hi: i32| (lo - p).max(p - hi).max(0);
+        let first = gap(px, x0, x1).max(gap(py, y0, y1));
+        let reach = (px - x0).max(x1 - px).max(py - y0).max(y1 - py).max(0);
+        let mut best: Option<Nearest> = None;
+        for r in first..=reach {
+            if let Some(b) = best {
+                // Points outside of the rings searched so far are further than this
+                if b.distance as i64 <= (r as i64 - 1) * self.cell as i64 {
+                    break;
+                }
+            }
+            for cell in ring((px, py), r, self.cells) {
+                for &id in self.buckets.get(&cell).into_iter().flatten() {
+                    let (point, distance) = nearest_on(self.segment(id), p);
+                    let candidate = Nearest {
+                        segment: id,
+                        point,
+                        distance,
+                    };
+                    let better = match best {
+                        Some(b) => (distance, id) < (b.distance, b.segment),
+                        None => true,
+                    };
+                    if better {
+                        best = Some(candidate);
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    /// Pairs of segments of different wires sharing at least one point, each pair once with
+    /// the lowest wire first.
+    pub fn touching_pairs(&self) -> Vec<(SegmentId, SegmentId)> {
+        let mut pairs = HashSet::new();
+        for bucket in self.buckets.values() {
+            for (k, &first) in bucket.iter().enumerate() {
+                for &second in &bucket[k + 1..] {
+                    let (first, second) = (first.min(second), first.max(second));
+                    if first.0 != second.0
+                        && !pairs.contains(&(first, second))
+                        && overlap(self.segment(first), self.segment(second)).is_some()
+                    {
+                        pairs.insert((first, second));
+                    }
+                }
+            }
+        }
+        let mut pairs: Vec<_> = pairs.into_iter().collect();
+        pairs.sort_unstable();
+        pairs
+    }
+
+    /// Every point where at least two wires meet, like `crossings` but looking at all the wires
+    /// at once instead of every pair of them.
+    pub fn crossings(&self) -> Vec<Crossing> {
+        let before: Vec<Vec<u32>> = self.wires.iter().map(|w| steps_before(w)).collect();
+        let mut visits = vec![];
+        for ((w1, i), (w2, j)) in self.touching_pairs() {
+            let (s1, s2) = (&self.wires[w1][i], &self.wires[w2][j]);
+            for p in segments_intersect(s1, s2) {
+                visits.push((p, w1, before[w1][i] + s1.steps_to(&p)));
+                visits.push((p, w2, before[w2][j] + s2.steps_to(&p)));
+            }
+        }
+        gather_crossings(visits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::generate::{random_moves, Rng};
+    use super::super::{crossings, split_input};
+    use super::*;
+
+    fn example() -> SegmentIndex {
+        SegmentIndex::new(&split_input("R8,U5,L5,D3\nU7,R6,D4,L4"), 3)
+    }
+
+    #[test]
+    fn test_queries() {
+        let index = example();
+        assert_eq!(index.in_rect(((4, 1), (5, 2))), vec![]);
+        assert_eq!(index.in_rect(((7, 1), (5, 4))), vec![(1, 2), (1, 3)]);
+        assert_eq!(index.at_point(&Point::new(3, 3)), vec![(0, 3), (1, 3)]);
+        assert_eq!(index.at_point(&Point { x: 3, y: 3, z: 1 }), vec![]);
+        assert_eq!(
+            index.nearest(&Point::new(4, 1)),
+            Some(Nearest {
+                segment: (0, 0),
+                point: Point::new(4, 0),
+                distance: 1
+            })
+        );
+        assert_eq!(
+            index.nearest(&Point::new(-10, 30)).unwrap().point,
+            Point::new(0, 7)
+        );
+        // Far from the wires with tiny cells, the empty rings in between are skipped
+        let fine = SegmentIndex::new(&split_input("R8,U5,L5,D3\nU7,R6,D4,L4"), 1);
+        let far = fine.nearest(&Point::new(-30_000, 1_000_000)).unwrap();
+        assert_eq!((far.point, far.distance), (Point::new(0, 7), 1_029_993));
+        // Diagonals only hold lattice points
+        let index = SegmentIndex::new(&split_input("UR4"), 2);
+        assert_eq!(index.in_rect(((1, 2), (1, 3))), vec![]);
+        assert_eq!(index.in_rect(((1, 2), (2, 3))), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn test_crossings_against_pairwise() {
+        let mut rng = Rng::new(0x1de);
+        for _ in 0..50 {
+            let wires: Vec<Wire> = (0..4)
+                .map(|_| Wire::from(random_moves(&mut rng, 10, 8, 6).join(",").as_str()))
+                .collect();
+            let index = SegmentIndex::new(&wires, rng.range(1, 6));
+            assert_eq!(index.crossings(), crossings(&wires));
+
+            let p = Point::new(rng.range(-20, 20), rng.range(-20, 20));
+            let closest = wires
+                .iter()
+                .flat_map(Wire::segments)
+                .flat_map(|s| s.points())
+                .map(|q| q.distance_from(&p))
+                .min();
+            assert_eq!(index.nearest(&p).map(|n| n.distance), closest);
+        }
+    }
+}
@@ -0,0 +1,297 @@
+//! Reading wires from any `BufRead` one move at a time, for inputs too big to fit in memory.
+//!
+//! `Moves` parses the moves of a single line lazily and hands the reader back once done, so
+//! the next line can be read from it. `Segments` turns the moves into segments as they come.
+//! `best_crossings` runs the sweep over blocks of the two wires: a block of the first wire is
+//! compared against the whole second wire, read again block by block, so only two blocks are
+//! ever in memory at once.
+
+use std::io;
+use std::io::BufRead;
+
+use super::{overlap, parse_step, step_end, sweep, Direction, Point, Segment};
+
+/// The moves of one wire, up to the end of the line
+pub struct Moves<R> {
+    reader: R,
+    token: Vec<u8>,
+    done: bool,
+}
+
+impl<R: BufRead> Moves<R> {
+    pub fn new(reader: R) -> Moves<R> {
+        Moves {
+            reader,
+            token: vec![],
+            done: false,
+        }
+    }
+
+    /// The reader, past the line once every move has been read
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads up to the next `,` or the end of the line, returns whether the wire goes on
+    fn read_token(&mut self) -> io::Result<bool> {
+        self.token.clear();
+        loop {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(false);
+            }
+            match buf.iter().position(|b| *b == b',' || *b == b'\n') {
+                Some(i) => {
+                    let more = buf[i] == b',';
+                    self.token.extend_from_slice(&buf[..i]);
+                    self.reader.consume(i + 1);
+                    return Ok(more);
+                }
+                None => {
+                    let n = buf.len();
+                    self.token.extend_from_slice(buf);
+                    self.reader.consume(n);
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Moves<R> {
+    type Item = Result<(Direction, u32), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.read_token() {
+            Ok(more) => self.done = !more,
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e.to_string()));
+            }
+        }
+        let token = String::from_utf8_lossy(&self.token);
+        let token = token.trim();
+        if token.is_empty() && self.done {
+            // Empty line, or a trailing comma
+            return None;
+        }
+        Some(parse_step(token))
+    }
+}
+
+/// The segments of a wire with the steps taken before each, stopping at the first error
+pub struct Segments<I> {
+    moves: I,
+    current: Point,
+    steps: u32,
+    failed: bool,
+}
+
+impl<I> Segments<I>
+where
+    I: Iterator<Item = Result<(Direction, u32), String>>,
+{
+    pub fn new(moves: I, start: Point) -> Segments<I> {
+        Segments {
+            moves,
+            current: start,
+            steps: 0,
+            failed: false,
+        }
+    }
+}
+
+impl<I> Iterator for Segments<I>
+where
+    I: Iterator<Item = Result<(Direction, u32), String>>,
+{
+    type Item = Result<(Segment, u32), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let (direction, count) = match self.moves.next()? {
+            Ok(m) => m,
+            Err(e) => {
+                self.failed = true;
+                return Some(Err(e));
+            }
+        };
+        let segment = match step_end(self.current, (direction, count)) {
+            Some(b) => Segment { a: self.current, b },
+            None => {
+                self.failed = true;
+                return Some(Err(String::from("Wire goes out of range")));
+            }
+        };
+        let before = self.steps;
+        self.steps = match before.checked_add(segment.len()) {
+            Some(steps) => steps,
+            None => {
+                self.failed = true;
+                return Some(Err(String::from("Wire too long to count its steps")));
+            }
+        };
+        self.current = segment.b;
+        Some(Ok((segment, before)))
+    }
+}
+
+/// Closest distance from the origin and fewest combined steps over the crossings
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct Best {
+    pub distance: Option<u32>,
+    pub steps: Option<u32>,
+}
+
+/// Up to `n` segments, with the steps taken before each
+type Block = (Vec<Segment>, Vec<u32>);
+
+fn take_block<I>(segments: &mut I, n: usize) -> Result<Block, String>
+where
+    I: Iterator<Item = Result<(Segment, u32), String>>,
+{
+    let mut block = (vec![], vec![]);
+    for s in segments.take(n) {
+        let (segment, before) = s?;
+        block.0.push(segment);
+        block.1.push(before);
+    }
+    Ok(block)
+}
+
+fn skip_line<R: BufRead>(reader: &mut R) -> io::Result<()> {
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(());
+        }
+        match buf.iter().position(|b| *b == b'\n') {
+            Some(i) => {
+                reader.consume(i + 1);
+                return Ok(());
+            }
+            None => {
+                let n = buf.len();
+                reader.consume(n);
+            }
+        }
+    }
+}
+
+impl Best {
+    fn update(&mut self, first: &Block, second: &Block) {
+        for (i, j) in sweep::touching_pairs(&first.0, &second.0) {
+            let (s1, s2) = (&first.0[i], &second.0[j]);
+            for p in overlap(s1, s2).unwrap().points() {
+                // Both wires start there
+                if p == Point::ORIGIN {
+                    continue;
+                }
+                let distance = p.distance_from_origin();
+                let steps = first.1[i] + s1.steps_to(&p) + second.1[j] + s2.steps_to(&p);
+                self.distance = Some(self.distance.map_or(distance, |d| d.min(distance)));
+                self.steps = Some(self.steps.map_or(steps, |s| s.min(steps)));
+            }
+        }
+    }
+}
+
+/// Best crossings of the first two wires of the input, both starting at the origin, holding at
+/// most `block` segments of each wire in memory. `open` gives a reader at the start of the
+/// input, it is called once more for every block of the first wire to read the second one again.
+pub fn best_crossings<R, F>(mut open: F, block: usize) -> Result<Best, String>
+where
+    R: BufRead,
+    F: FnMut() -> io::Result<R>,
+{
+    let block = block.max(1);
+    let mut best = Best::default();
+    let reader = open().map_err(|e| e.to_string())?;
+    let mut first = Segments::new(Moves::new(reader), Point::ORIGIN);
+    loop {
+        let block1 = take_block(&mut first, block)?;
+        if block1.0.is_empty() {
+            return Ok(best);
+        }
+        let mut reader = open().map_err(|e| e.to_string())?;
+        skip_line(&mut reader).map_err(|e| e.to_string())?;
+        let mut second = Segments::new(Moves::new(reader), Point::ORIGIN);
+        loop {
+            let block2 = take_block(&mut second, block)?;
+            if block2.0.is_empty() {
+                break;
+            }
+            best.update(&block1, &block2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::generate::{Generator, Rng};
+    use super::super::{best_crossing, split_input, steps_before, Criterion, Wire};
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_moves() {
+        let mut input = Cursor::new("R8,U5,L5,D3\r\nUR7,V3:-2:1,D4\n");
+        for line in ["R8,U5,L5,D3", "UR7,V3:-2:1,D4"] {
+            let moves = Moves::new(&mut input);
+            let (segments, steps): (Vec<_>, Vec<_>) = Segments::new(moves, Point::ORIGIN)
+                .map(Result::unwrap)
+                .unzip();
+            let expected = Wire::from(line).segments();
+            assert_eq!(steps, steps_before(&expected));
+            assert_eq!(segments, expected);
+        }
+        assert_eq!(Moves::new(&mut input).next(), None);
+
+        let moves: Vec<_> = Moves::new(Cursor::new("R8,X5,U2")).collect();
+        assert_eq!(moves[0], Ok((Direction::Right, 8)));
+        assert_eq!(moves[1], Err(String::from("Bad move \"X5\"")));
+        for bad in ["R1,,U2", "V1", "V1:2:3:4", "U-3"] {
+            assert!(Moves::new(Cursor::new(bad)).any(|m| m.is_err()), "{}", bad);
+        }
+        let mut segments = Segments::new(Moves::new(Cursor::new("R1,X,U2")), Point::ORIGIN);
+        assert!(segments.nth(1).unwrap().is_err());
+        assert_eq!(segments.next(), None);
+        let far = Moves::new(Cursor::new("V2147483647:0,V1:0"));
+        assert!(Segments::new(far, Point::ORIGIN).any(|s| s.is_err()));
+    }
+
+    #[test]
+    fn test_best_crossings() {
+        let mut rng = Rng::new(46);
+        let generator = Generator {
+            moves: 60,
+            bounds: ((-500, -500), (500, 500)),
+            diagonals: true,
+            ..Default::default()
+        };
+        for _ in 0..5 {
+            let input = generator.input(&mut rng).unwrap();
+            let wires = split_input(&input);
+            let expected = Best {
+                distance: best_crossing(&wires, Criterion::Distance)
+                    .map(|c| c.point.distance_from_origin()),
+                steps: best_crossing(&wires, Criterion::Steps).map(|c| c.combined_steps()),
+            };
+            for block in [1, 7, 1_000] {
+                let open = || Ok(Cursor::new(input.as_bytes()));
+                assert_eq!(best_crossings(open, block), Ok(expected), "{}", input);
+            }
+        }
+        let open = || Ok(Cursor::new("R8,U5,L5,D3\nU7,R6,D4,L4"));
+        let best = best_crossings(open, 2).unwrap();
+        assert_eq!((best.distance, best.steps), (Some(6), Some(30)));
+        assert_eq!(
+            best_crossings(|| Ok(Cursor::new("R8")), 2),
+            Ok(Best::default())
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! SVG drawing of wires and their crossings, to inspect an input visually.
+//!
+//! Wires are drawn from above: the `z` axis is dropped and `y` points up like in the puzzle.
+//! The drawing is scaled to fit a square of the requested size, the legend goes below it.
+
+use std::fmt::Write;
+
+use super::{best_crossing, crossings, Criterion, Crossing, Point, Wire};
+
+const MARGIN: f64 = 20.0;
+const LEGEND_LINE: f64 = 20.0;
+
+/// Smallest size leaving room for the drawing between the margins
+pub const MIN_SIZE: u32 = 2 * MARGIN as u32 + 1;
+
+/// Colours of the wires, picked in turn
+const PALETTE: [&str; 6] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#9467bd", "#8c564b", "#17becf",
+];
+const CROSSING: &str = "#7f7f7f";
+const CLOSEST: &str = "#d62728";
+const FASTEST: &str = "#e377c2";
+
+/// Maps wire coordinates to the picture
+struct Frame {
+    x0: i32,
+    y1: i32,
+    scale: f64,
+}
+
+impl Frame {
+    fn fit(points: &[Point], size: f64) -> Frame {
+        let x0 = points.iter().map(|p| p.x).min().unwrap_or(0);
+        let x1 = points.iter().map(|p| p.x).max().unwrap_or(0);
+        let y0 = points.iter().map(|p| p.y).min().unwrap_or(0);
+        let y1 = points.iter().map(|p| p.y).max().unwrap_or(0);
+        let extent = (x1 - x0).max(y1 - y0).max(1) as f64;
+        Frame {
+            x0,
+            y1,
+            scale: (size - 2.0 * MARGIN) / extent,
+        }
+    }
+
+    fn map(&self, p: &Point) -> (f64, f64) {
+        (
+            MARGIN + (p.x - self.x0) as f64 * self.scale,
+            MARGIN + (self.y1 - p.y) as f64 * self.scale,
+        )
+    }
+}
+
+/// Vertices of a wire, from its start
+fn vertices(wire: &Wire) -> Vec<Point> {
+    let mut points = vec![wire.start()];
+    points.extend(wire.segments().iter().map(|s| s.b));
+    points
+}
+
+fn ring(svg: &mut String, (x, y): (f64, f64), r: f64, colour: &str) {
+    writeln!(
+        svg,
+        r#"<circle cx="{:.1}" cy="{:.1}" r="{}" fill="none" stroke="{}" stroke-width="2"/>"#,
+        x, y, r, colour
+    )
+    .unwrap();
+}
+
+fn dot(svg: &mut String, (x, y): (f64, f64), colour: &str) {
+    writeln!(
+        svg,
+        r#"<circle cx="{:.1}" cy="{:.1}" r="3" fill="{}"/>"#,
+        x, y, colour
+    )
+    .unwrap();
+}
+
+fn label(svg: &mut String, (x, y): (f64, f64), text: &str) {
+    writeln!(
+        svg,
+        r#"<text x="{:.1}" y="{:.1}" font-family="sans-serif" font-size="12">{}</text>"#,
+        x,
+        y + 4.0,
+        text
+    )
+    .unwrap();
+}
+
+fn describe(what: &str, c: &Crossing, value: String) -> String {
+    format!("{} ({}, {}): {}", what, c.point.x, c.point.y, value)
+}
+
+/// Draws the wires in a `size` pixels wide picture: one polyline per wire, their starts, every
+/// crossing, the crossing closest to the origin and the one with the fewest combined steps.
+/// Fails when `size` is below [`MIN_SIZE`].
+pub fn render(wires: &[Wire], size: u32) -> Result<String, String> {
+    if size < MIN_SIZE {
+        return Err(format!("Size {} is less than {} pixels", size, MIN_SIZE));
+    }
+    let size = size as f64;
+    let polylines: Vec<Vec<Point>> = wires.iter().map(vertices).collect();
+    let all: Vec<Point> = polylines.iter().flatten().copied().collect();
+    let frame = Frame::fit(&all, size);
+    let closest = best_crossing(wires, Criterion::Distance);
+    let fastest = best_crossing(wires, Criterion::Steps);
+
+    let legend_lines = wires.len() + 2 + closest.is_some() as usize + fastest.is_some() as usize;
+    let height = size + legend_lines as f64 * LEGEND_LINE + MARGIN;
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="0 0 {0} {1}">"#,
+        size, height
+    )
+    .unwrap();
+    writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+
+    for (i, points) in polylines.iter().enumerate() {
+        let coordinates: Vec<String> = points
+            .iter()
+            .map(|p| {
+                let (x, y) = frame.map(p);
+                format!("{:.1},{:.1}", x, y)
+            })
+            .collect();
+        writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"/>"#,
+            coordinates.join(" "),
+            PALETTE[i % PALETTE.len()]
+        )
+        .unwrap();
+    }
+    for c in crossings(wires).iter().filter(|c| !c.is_start()) {
+        dot(&mut svg, frame.map(&c.point), CROSSING);
+    }
+    let mut starts: Vec<(i32, i32)> = wires.iter().map(|w| (w.start().x, w.start().y)).collect();
+    starts.sort_unstable();
+    starts.dedup();
+    for (x, y) in starts {
+        ring(&mut svg, frame.map(&Point::new(x, y)), 5.0, "black");
+    }
+    if let Some(c) = &closest {
+        ring(&mut svg, frame.map(&c.point), 7.0, CLOSEST);
+    }
+    if let Some(c) = &fastest {
+        ring(&mut svg, frame.map(&c.point), 10.0, FASTEST);
+    }
+
+    let mut y = size + LEGEND_LINE / 2.0;
+    let mut entry = |svg: &mut String, text: String, colour: &str, line: bool| {
+        if line {
+            writeln!(
+                svg,
+                r#"<line x1="{0}" y1="{1:.1}" x2="{2}" y2="{1:.1}" stroke="{3}" stroke-width="2"/>"#,
+                MARGIN,
+                y,
+                MARGIN + 20.0,
+                colour
+            )
+            .unwrap();
+        } else {
+            ring(svg, (MARGIN + 10.0, y), 5.0, colour);
+        }
+        label(svg, (MARGIN + 30.0, y), &text);
+        y += LEGEND_LINE;
+    };
+    for (i, wire) in wires.iter().enumerate() {
+        let steps: u32 = wire.segments().iter().map(|s| s.len()).sum();
+        let text = format!("wire {}: {} steps", i + 1, steps);
+        entry(&mut svg, text, PALETTE[i % PALETTE.len()], true);
+    }
+    entry(&mut svg, String::from("start"), "black", false);
+    entry(&mut svg, String::from("crossing"), CROSSING, false);
+    if let Some(c) = &closest {
+        let distance = c.point.distance_from_origin();
+        let text = describe("closest crossing", c, format!("distance {}", distance));
+        entry(&mut svg, text, CLOSEST, false);
+    }
+    if let Some(c) = &fastest {
+        let text = describe("fewest steps", c, format!("{} steps", c.combined_steps()));
+        entry(&mut svg, text, FASTEST, false);
+    }
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::split_input;
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let wires = split_input("R8,U5,L5,D3\nU7,R6,D4,L4");
+        let svg = render(&wires, 200).unwrap();
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        // 8 units wide and 7 high, 20 pixels per unit, with the origin bottom left
+        assert!(svg.contains(r#"points="20.0,160.0 180.0,160.0 180.0,60.0"#));
+        assert!(svg.contains(r#"points="20.0,160.0 20.0,20.0 140.0,20.0"#));
+        // Two crossings, the closest is (3, 3) and the fastest (6, 5)
+        assert_eq!(svg.matches(CROSSING).count(), 3);
+        assert!(svg.contains(r#"cx="80.0" cy="100.0" r="7""#));
+        assert!(svg.contains(r#"cx="140.0" cy="60.0" r="10""#));
+        assert!(svg.contains("closest crossing (3, 3): distance 6"));
+        assert!(svg.contains("fewest steps (6, 5): 30 steps"));
+        assert!(svg.contains("wire 1: 21 steps"));
+    }
+
+    #[test]
+    fn test_render_degenerate() {
+        let svg = render(&split_input("U0\nR0"), 100).unwrap();
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        assert!(!svg.contains("closest crossing"));
+        assert!(render(&split_input("U1\nR1"), MIN_SIZE).is_ok());
+        assert!(render(&split_input("U1\nR1"), MIN_SIZE - 1).is_err());
+    }
+
+    #[test]
+    fn test_render_starts() {
+        let wires = vec![
+            Wire::from("R4"),
+            Wire::from("U4").starting_at(Point::new(2, -2)),
+        ];
+        let svg = render(&wires, 100).unwrap();
+        assert!(svg.contains(r#"points="50.0,80.0 50.0,20.0"#));
+        assert!(svg.contains(r#"cx="20.0" cy="50.0" r="5""#));
+        assert!(svg.contains(r#"cx="50.0" cy="80.0" r="5""#));
+        assert!(svg.contains("closest crossing (2, 0): distance 2"));
+    }
+}
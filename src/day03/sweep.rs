@@ -0,0 +1,220 @@
+//! Sweep-line search for the segments of two wires that touch, specialised to horizontal and
+//! vertical segments.
+//!
+//! A vertical line sweeps from left to right over the horizontal segments, keeping the ones it
+//! currently crosses ordered by `y`. Each vertical segment met on the way only needs a range
+//! query over that ordered set. Segments lying on the same line are handled separately by
+//! sorting each line's intervals.
+//!
+//! For `n` axis-aligned segments and `k` touching pairs this is `O((n + k) log n)` as long as
+//! the segments of a single wire don't pile up on top of each other. Segments of the same wire
+//! lying on one line and overlapping are all kept open together and scanned for every segment
+//! that follows on the line, up to `O(n²)` when a wire goes back and forth over itself.
+//!
+//! Every layer is swept at once, the active segments being ordered by `(z, y)`. Diagonal and
+//! vector segments, and the ones going from a layer to another, are not handled by the sweep,
+//! they are compared against every segment of the other wire: `s` of them add `O(s n)`.
+
+use std::cmp::{max, min};
+use std::collections::BTreeMap;
+
+use super::{overlap, Segment};
+
+/// A segment of one of the two wires
+type Tag = (usize, usize);
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum Event {
+    // Order matters: at a given x, segments starting there must be active for the
+    // verticals at that x, and the ones ending there must still be.
+    Insert { zy: (i32, i32), tag: Tag },
+    Query { z: i32, lo: i32, hi: i32, tag: Tag },
+    Remove { zy: (i32, i32), tag: Tag },
+}
+
+fn is_vertical(s: &Segment) -> bool {
+    s.a.x == s.b.x && s.a.y != s.b.y
+}
+
+/// Pairs `(i, j)` such that `first[i]` and `second[j]` share at least one point.
+pub(super) fn touching_pairs(first: &[Segment], second: &[Segment]) -> Vec<(usize, usize)> {
+    let tagged = || {
+        first
+            .iter()
+            .enumerate()
+            .map(|(i, s)| ((0, i), s))
+            .chain(second.iter().enumerate().map(|(j, s)| ((1, j), s)))
+            .filter(|(_, s)| s.is_axis_aligned())
+    };
+    let mut pairs = vec![];
+    let slanted = |segments: &[Segment]| -> Vec<usize> {
+        (0..segments.len())
+            .filter(|i| !segments[*i].is_axis_aligned())
+            .collect()
+    };
+    for i in slanted(first) {
+        for (j, s2) in second.iter().enumerate() {
+            if overlap(&first[i], s2).is_some() {
+                pairs.push((i, j));
+            }
+        }
+    }
+    for j in slanted(second) {
+        for (i, s1) in first.iter().enumerate() {
+            if s1.is_axis_aligned() && overlap(s1, &second[j]).is_some() {
+                pairs.push((i, j));
+            }
+        }
+    }
+    let mut push = |t1: Tag, t2: Tag| match (t1, t2) {
+        ((0, i), (1, j)) | ((1, j), (0, i)) => pairs.push((i, j)),
+        _ => {}
+    };
+
+    // Horizontal against vertical
+    let mut events = vec![];
+    for (tag, s) in tagged() {
+        let (lo, hi) = (min(s.a.x, s.b.x), max(s.a.x, s.b.x));
+        if is_vertical(s) {
+            let (y0, y1) = (min(s.a.y, s.b.y), max(s.a.y, s.b.y));
+            events.push((
+                lo,
+                Event::Query {
+                    z: s.a.z,
+                    lo: y0,
+                    hi: y1,
+                    tag,
+                },
+            ));
+        } else {
+            let zy = (s.a.z, s.a.y);
+            events.push((lo, Event::Insert { zy, tag }));
+            events.push((hi, Event::Remove { zy, tag }));
+        }
+    }
+    events.sort_unstable();
+    let mut active: BTreeMap<(i32, i32), Vec<Tag>> = BTreeMap::new();
+    for (_, event) in events {
+        match event {
+            Event::Insert { zy, tag } => active.entry(zy).or_default().push(tag),
+            Event::Remove { zy, tag } => {
+                let tags = active.get_mut(&zy).unwrap();
+                let i = tags.iter().position(|t| *t == tag).unwrap();
+                tags.swap_remove(i);
+                if tags.is_empty() {
+                    active.remove(&zy);
+                }
+            }
+            Event::Query { z, lo, hi, tag } => {
+                for (_, tags) in active.range((z, lo)..=(z, hi)) {
+                    for t in tags {
+                        push(*t, tag);
+                    }
+                }
+            }
+        }
+    }
+
+    // Segments running along the same line: (line, start, end, tag)
+    let mut along = vec![];
+    for (tag, s) in tagged() {
+        if is_vertical(s) {
+            let line = (1, s.a.z, s.a.x);
+            along.push((line, min(s.a.y, s.b.y), max(s.a.y, s.b.y), tag));
+        } else {
+            let line = (0, s.a.z, s.a.y);
+            along.push((line, min(s.a.x, s.b.x), max(s.a.x, s.b.x), tag));
+        }
+    }
+    along.sort_unstable();
+    let mut open: Vec<(i32, Tag)> = vec![];
+    let mut line = None;
+    for (l, lo, hi, tag) in along {
+        if line != Some(l) {
+            open.clear();
+            line = Some(l);
+        }
+        open.retain(|(end, _)| *end >= lo);
+        for (_, t) in &open {
+            push(*t, tag);
+        }
+        open.push((hi, tag));
+    }
+
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::generate::{random_moves, Generator, Rng};
+    use super::super::*;
+    use super::*;
+    use std::time::Instant;
+
+    fn naive_pairs(first: &[Segment], second: &[Segment]) -> Vec<(usize, usize)> {
+        let mut pairs = vec![];
+        for (i, s1) in first.iter().enumerate() {
+            for (j, s2) in second.iter().enumerate() {
+                if overlap(s1, s2).is_some() {
+                    pairs.push((i, j));
+                }
+            }
+        }
+        pairs
+    }
+
+    #[test]
+    fn test_touching_pairs() {
+        let w1 = Wire::from("R8,U5,L5,D3").segments();
+        let w2 = Wire::from("U7,R6,D4,L4").segments();
+        let mut pairs = touching_pairs(&w1, &w2);
+        pairs.sort();
+        assert_eq!(pairs, vec![(0, 0), (2, 2), (3, 3)]);
+    }
+
+    #[test]
+    fn test_against_naive() {
+        let mut rng = Rng::new(42);
+        for _ in 0..500 {
+            let w1 = Wire::from(random_moves(&mut rng, 12, 6, 8).join(",").as_str()).segments();
+            let w2 = Wire::from(random_moves(&mut rng, 12, 6, 8).join(",").as_str()).segments();
+            let mut pairs = touching_pairs(&w1, &w2);
+            pairs.sort();
+            assert_eq!(pairs, naive_pairs(&w1, &w2));
+        }
+    }
+
+    /// `cargo test --release bench_sweep -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_sweep() {
+        let mut rng = Rng::new(7);
+        for &n in &[1_000, 5_000, 20_000] {
+            let generator = Generator {
+                moves: n,
+                bounds: ((-100_000, -100_000), (100_000, 100_000)),
+                ..Default::default()
+            };
+            let (w1, w2) = generator.pair(&mut rng).unwrap();
+            let (w1, w2) = (w1.segments(), w2.segments());
+
+            let start = Instant::now();
+            let mut fast = touching_pairs(&w1, &w2);
+            let sweep = start.elapsed();
+            let start = Instant::now();
+            let slow = naive_pairs(&w1, &w2);
+            let naive = start.elapsed();
+
+            fast.sort();
+            assert_eq!(fast, slow);
+            println!(
+                "{} segments per wire, {} pairs: sweep {:?}, naive {:?}, {:.1}x faster",
+                n,
+                fast.len(),
+                sweep,
+                naive,
+                naive.as_secs_f64() / sweep.as_secs_f64()
+            );
+        }
+    }
+}
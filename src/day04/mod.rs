@@ -1,72 +1,83 @@
 use std::fmt;
 use std::fmt::{Error, Formatter};
 
+pub mod rules;
+pub mod spec;
+
+use self::rules::Rule;
+
+/// Number of digits of the puzzle passwords
+const PUZZLE_DIGITS: usize = 6;
+
 #[derive(Debug, PartialEq, PartialOrd)]
-struct Password {
-    code: [u32; 6],
+pub struct Password {
+    code: Vec<u32>,
+    radix: u32,
 }
 
-impl Password {
-    fn from_s(s: &str) -> Password {
-        let v: Vec<u32> = s
-            .chars()
-            .into_iter()
-            .map(|c| c.to_digit(10).unwrap())
-            .collect();
-        assert_eq!(v.len(), 6);
-        let mut code: [u32; 6] = [0; 6];
-        code.copy_from_slice(&v[0..6]);
-        Password { code }
-    }
-
-    fn from_u(mut u: u32) -> Password {
-        let mut code: [u32; 6] = [0; 6];
-        for i in 0..code.len() {
-            code[code.len() - i - 1] = u % 10;
-            u /= 10;
-        }
-        Password { code }
+fn check_radix(radix: u32) -> Result<(), String> {
+    if (2..=36).contains(&radix) {
+        Ok(())
+    } else {
+        Err(format!("radix {} is not between 2 and 36", radix))
     }
+}
 
-    fn is_valid(&self) -> bool {
-        // 2 same consecutive digits
-        let (mut test_a, mut test_b) = (false, true);
-        for i in 0..self.code.len() - 1 {
-            if self.code[i] == self.code[i + 1] {
-                test_a = true;
-            }
-            if self.code[i + 1] < self.code[i] {
-                test_b = false;
-            }
+impl Password {
+    /// Reads the digits of a password in base `radix`, letters standing for the digits over 9.
+    pub fn from_s(s: &str, radix: u32) -> Result<Password, String> {
+        check_radix(radix)?;
+        if s.is_empty() {
+            return Err(String::from("empty password"));
         }
-        test_a && test_b
+        let code = s
+            .chars()
+            .map(|c| {
+                c.to_digit(radix)
+                    .ok_or_else(|| format!("{:?} is not a digit in base {}", c, radix))
+            })
+            .collect::<Result<Vec<u32>, String>>()?;
+        Ok(Password { code, radix })
     }
 
-    fn is_valid_b(&self) -> bool {
-        // 2 same consecutive digits
-        let (mut test_a, mut test_b) = (false, true);
-        if self.code[0] == self.code[1] && self.code[1] != self.code[2] {
-            test_a = true;
+    /// Writes `u` with `len` digits in base `radix`, padded with zeros.
+    pub fn from_u(mut u: u64, len: usize, radix: u32) -> Result<Password, String> {
+        check_radix(radix)?;
+        if len == 0 {
+            return Err(String::from("empty password"));
         }
-        if self.code[self.code.len() - 3] != self.code[self.code.len() - 2]
-            && self.code[self.code.len() - 2] == self.code[self.code.len() - 1]
-        {
-            test_a = true;
+        let value = u;
+        let mut code = vec![0; len];
+        for digit in code.iter_mut().rev() {
+            *digit = (u % radix as u64) as u32;
+            u /= radix as u64;
         }
-        for i in 0..self.code.len() - 3 {
-            if self.code[i] != self.code[i + 1]
-                && self.code[i + 1] == self.code[i + 2]
-                && self.code[i + 2] != self.code[i + 3]
-            {
-                test_a = true;
-            }
-        }
-        for i in 0..self.code.len() - 1 {
-            if self.code[i + 1] < self.code[i] {
-                test_b = false;
-            }
+        if u > 0 {
+            return Err(format!(
+                "{} has more than {} digits in base {}",
+                value, len, radix
+            ));
         }
-        test_a && test_b
+        Ok(Password { code, radix })
+    }
+
+    /// The number the digits stand for, `None` if it does not fit
+    pub fn to_u(&self) -> Option<u64> {
+        self.code.iter().try_fold(0u64, |u, d| {
+            u.checked_mul(self.radix as u64)?.checked_add(*d as u64)
+        })
+    }
+
+    pub fn radix(&self) -> u32 {
+        self.radix
+    }
+
+    pub fn digits(&self) -> &[u32] {
+        &self.code
+    }
+
+    pub fn satisfies(&self, rule: &dyn Rule) -> bool {
+        rule.check(self)
     }
 }
 
@@ -75,7 +86,10 @@ impl fmt::Display for Password {
         write!(
             f,
             "{}",
-            self.code.iter().map(|x| x.to_string()).collect::<String>()
+            self.code
+                .iter()
+                .map(|x| std::char::from_digit(*x, self.radix).unwrap())
+                .collect::<String>()
         )
     }
 }
@@ -84,45 +98,47 @@ fn process_input(s: &str) -> Vec<&str> {
     s.split("-").collect::<Vec<&str>>()
 }
 
-pub fn entry_a(wires: String) -> String {
-    let v = process_input(wires.as_str());
-    let p1 = v.get(0).unwrap().parse().unwrap();
-    let p2 = v.get(1).unwrap().parse().unwrap();
+/// Number of codes in `range` written with `len` digits in base `radix` that follow `rule`
+pub fn count<I>(range: I, len: usize, radix: u32, rule: &dyn Rule) -> Result<usize, String>
+where
+    I: IntoIterator<Item = u64>,
+{
     let mut count = 0;
-    for p in p1..p2 {
-        let pw = Password::from_u(p);
-        if pw.is_valid() {
+    for u in range {
+        if Password::from_u(u, len, radix)?.satisfies(rule) {
             count += 1;
         }
     }
-    count.to_string()
+    Ok(count)
 }
 
-pub fn entry_b(wires: String) -> String {
-    let v = process_input(wires.as_str());
-    let p1 = v.get(0).unwrap().parse().unwrap();
+/// Counts the passwords of the `LO-HI` input, both ends included
+fn count_input(input: &str, rule: &dyn Rule) -> String {
+    let v = process_input(input);
+    let p1 = v.first().unwrap().parse().unwrap();
     let p2 = v.get(1).unwrap().parse().unwrap();
-    let mut count = 0;
-    for p in p1..p2 {
-        let pw = Password::from_u(p);
-        if pw.is_valid_b() {
-            count += 1;
-        }
-    }
-    count.to_string()
+    count(p1..=p2, PUZZLE_DIGITS, 10, rule).unwrap().to_string()
+}
+
+pub fn entry_a(wires: String) -> String {
+    count_input(&wires, &rules::part_a())
+}
+
+pub fn entry_b(wires: String) -> String {
+    count_input(&wires, &rules::part_b())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::env::split_paths;
 
     #[test]
     fn test_from() {
         assert_eq!(
-            Password::from_s("123456"),
+            Password::from_s("123456", 10).unwrap(),
             Password {
-                code: [1, 2, 3, 4, 5, 6]
+                code: vec![1, 2, 3, 4, 5, 6],
+                radix: 10
             }
         );
     }
@@ -130,27 +146,48 @@ mod tests {
     #[test]
     fn test_to() {
         let p = Password {
-            code: [1, 2, 3, 4, 5, 6],
+            code: vec![1, 2, 3, 4, 5, 6],
+            radix: 10,
         };
         assert_eq!(p.to_string(), "123456",);
     }
 
     #[test]
     fn test_is_valid() {
-        assert!(Password::from_s("111111").is_valid());
-        assert!(!Password::from_s("223450").is_valid());
-        assert!(!Password::from_s("123789").is_valid());
+        assert!(Password::from_s("111111", 10)
+            .unwrap()
+            .satisfies(&rules::part_a()));
+        assert!(!Password::from_s("223450", 10)
+            .unwrap()
+            .satisfies(&rules::part_a()));
+        assert!(!Password::from_s("123789", 10)
+            .unwrap()
+            .satisfies(&rules::part_a()));
     }
 
     #[test]
     fn test_is_valid_b() {
-        assert!(Password::from_s("112233").is_valid_b());
-        assert!(Password::from_s("112344").is_valid_b());
-        assert!(Password::from_s("111344").is_valid_b());
-        assert!(Password::from_s("111122").is_valid_b());
-        assert!(Password::from_s("112222").is_valid_b());
-        assert!(!Password::from_s("123444").is_valid_b());
-        assert!(!Password::from_s("126668").is_valid_b());
+        assert!(Password::from_s("112233", 10)
+            .unwrap()
+            .satisfies(&rules::part_b()));
+        assert!(Password::from_s("112344", 10)
+            .unwrap()
+            .satisfies(&rules::part_b()));
+        assert!(Password::from_s("111344", 10)
+            .unwrap()
+            .satisfies(&rules::part_b()));
+        assert!(Password::from_s("111122", 10)
+            .unwrap()
+            .satisfies(&rules::part_b()));
+        assert!(Password::from_s("112222", 10)
+            .unwrap()
+            .satisfies(&rules::part_b()));
+        assert!(!Password::from_s("123444", 10)
+            .unwrap()
+            .satisfies(&rules::part_b()));
+        assert!(!Password::from_s("126668", 10)
+            .unwrap()
+            .satisfies(&rules::part_b()));
     }
 
     #[test]
@@ -161,13 +198,16 @@ mod tests {
     #[test]
     fn test_ord() {
         let p1 = Password {
-            code: [1, 2, 3, 4, 5, 5],
+            code: vec![1, 2, 3, 4, 5, 5],
+            radix: 10,
         };
         let p2 = Password {
-            code: [1, 2, 3, 4, 5, 6],
+            code: vec![1, 2, 3, 4, 5, 6],
+            radix: 10,
         };
         let p3 = Password {
-            code: [1, 2, 0, 4, 5, 6],
+            code: vec![1, 2, 0, 4, 5, 6],
+            radix: 10,
         };
         assert!(p1 < p2);
         assert!(p3 < p1);
@@ -176,10 +216,45 @@ mod tests {
     #[test]
     fn test_from_u() {
         assert_eq!(
-            Password::from_u(123456),
+            Password::from_u(123456, 6, 10).unwrap(),
             Password {
-                code: [1, 2, 3, 4, 5, 6]
+                code: vec![1, 2, 3, 4, 5, 6],
+                radix: 10
             }
         );
     }
+
+    #[test]
+    fn test_radix() {
+        let p = Password::from_s("0fF7", 16).unwrap();
+        assert_eq!(p.digits(), &[0, 15, 15, 7]);
+        assert_eq!(p.to_string(), "0ff7");
+        assert_eq!(p.to_u(), Some(0xff7));
+        assert!(!p.satisfies(&rules::part_a()));
+        assert_eq!(Password::from_u(0xff7, 4, 16), Ok(p));
+
+        let binary = Password::from_u(0b0111, 4, 2).unwrap();
+        assert_eq!(binary.to_string(), "0111");
+        assert!(binary.satisfies(&rules::part_a()) && !binary.satisfies(&rules::part_b()));
+        assert!(Password::from_s("1122", 3)
+            .unwrap()
+            .satisfies(&rules::part_b()));
+        assert!(Password::from_s("7", 10).unwrap().digits().len() == 1);
+        let long = Password::from_s("123456788999", 10).unwrap();
+        assert!(long.satisfies(&rules::part_b()));
+        assert_eq!(long.to_u(), Some(123456788999));
+
+        assert_eq!(
+            Password::from_s("129", 9),
+            Err(String::from("'9' is not a digit in base 9"))
+        );
+        assert!(Password::from_s("", 10).is_err());
+        assert!(Password::from_s("12", 37).is_err());
+        assert!(Password::from_u(5, 2, 1).is_err());
+        assert_eq!(
+            Password::from_u(1000, 3, 10),
+            Err(String::from("1000 has more than 3 digits in base 10"))
+        );
+        assert_eq!(Password::from_s("zzzzzzzzzzzzzz", 36).unwrap().to_u(), None);
+    }
 }
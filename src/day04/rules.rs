@@ -0,0 +1,208 @@
+//! Rules a password may have to follow, built from small pieces so that a new set of criteria
+//! is a combination of rules rather than new code.
+
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+use super::Password;
+
+pub trait Rule {
+    fn check(&self, p: &Password) -> bool;
+}
+
+/// Digits never decrease from left to right
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct NonDecreasing;
+
+/// Number of digits
+#[derive(PartialEq, Debug, Clone)]
+pub struct Length(pub RangeInclusive<usize>);
+
+/// Number of runs of the same digit whose length is in `length`, a run being as long as
+/// possible: `1113` has a run of 3 ones, not a run of 2.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Runs {
+    pub length: RangeInclusive<usize>,
+    pub count: RangeInclusive<usize>,
+}
+
+/// Every digit is one of these
+#[derive(PartialEq, Debug, Clone)]
+pub struct Digits(pub Vec<u32>);
+
+/// No three consecutive digits appear again further on, `123123` repeats `123`
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct NoRepeatedTriplet;
+
+/// Sum of the digits
+#[derive(PartialEq, Debug, Clone)]
+pub struct DigitSum(pub RangeInclusive<u32>);
+
+/// Sum of the digits modulo `modulus`, parity being modulo 2
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct SumModulo {
+    pub modulus: u32,
+    pub remainder: u32,
+}
+
+/// Every rule holds, true when there are none
+pub struct And(pub Vec<Box<dyn Rule>>);
+
+/// Some rule holds, false when there are none
+pub struct Or(pub Vec<Box<dyn Rule>>);
+
+pub struct Not(pub Box<dyn Rule>);
+
+impl Runs {
+    /// At least one run of two digits or more: some adjacent digits are the same
+    pub fn has_pair() -> Runs {
+        Runs {
+            length: 2..=usize::MAX,
+            count: 1..=usize::MAX,
+        }
+    }
+
+    /// At least one run of exactly two digits
+    pub fn has_exact_pair() -> Runs {
+        Runs {
+            length: 2..=2,
+            count: 1..=usize::MAX,
+        }
+    }
+}
+
+impl SumModulo {
+    pub fn even() -> SumModulo {
+        SumModulo {
+            modulus: 2,
+            remainder: 0,
+        }
+    }
+
+    pub fn odd() -> SumModulo {
+        SumModulo {
+            modulus: 2,
+            remainder: 1,
+        }
+    }
+}
+
+impl Rule for NonDecreasing {
+    fn check(&self, p: &Password) -> bool {
+        p.digits().windows(2).all(|w| w[0] <= w[1])
+    }
+}
+
+impl Rule for Length {
+    fn check(&self, p: &Password) -> bool {
+        self.0.contains(&p.digits().len())
+    }
+}
+
+impl Rule for Runs {
+    fn check(&self, p: &Password) -> bool {
+        let runs = p
+            .digits()
+            .chunk_by(|a, b| a == b)
+            .filter(|run| self.length.contains(&run.len()))
+            .count();
+        self.count.contains(&runs)
+    }
+}
+
+impl Rule for Digits {
+    fn check(&self, p: &Password) -> bool {
+        p.digits().iter().all(|d| self.0.contains(d))
+    }
+}
+
+impl Rule for NoRepeatedTriplet {
+    fn check(&self, p: &Password) -> bool {
+        let mut seen = HashSet::new();
+        p.digits().windows(3).all(|w| seen.insert(w))
+    }
+}
+
+impl Rule for DigitSum {
+    fn check(&self, p: &Password) -> bool {
+        self.0.contains(&p.digits().iter().sum())
+    }
+}
+
+impl Rule for SumModulo {
+    fn check(&self, p: &Password) -> bool {
+        p.digits().iter().sum::<u32>() % self.modulus == self.remainder
+    }
+}
+
+impl Rule for And {
+    fn check(&self, p: &Password) -> bool {
+        self.0.iter().all(|r| r.check(p))
+    }
+}
+
+impl Rule for Or {
+    fn check(&self, p: &Password) -> bool {
+        self.0.iter().any(|r| r.check(p))
+    }
+}
+
+impl Rule for Not {
+    fn check(&self, p: &Password) -> bool {
+        !self.0.check(p)
+    }
+}
+
+/// Rules of the first part of the puzzle
+pub fn part_a() -> And {
+    And(vec![Box::new(Runs::has_pair()), Box::new(NonDecreasing)])
+}
+
+/// Rules of the second part of the puzzle
+pub fn part_b() -> And {
+    And(vec![
+        Box::new(Runs::has_exact_pair()),
+        Box::new(NonDecreasing),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p(s: &str) -> Password {
+        Password::from_s(s, 10).unwrap()
+    }
+
+    #[test]
+    fn test_rules() {
+        assert!(NonDecreasing.check(&p("1123")) && !NonDecreasing.check(&p("1213")));
+        assert!(Length(4..=6).check(&p("1234")) && !Length(4..=6).check(&p("123")));
+        let triples = Runs {
+            length: 3..=3,
+            count: 2..=2,
+        };
+        assert!(triples.check(&p("1112333")) && !triples.check(&p("1111333")));
+        assert!(Digits(vec![1, 3]).check(&p("1331")) && !Digits(vec![1, 3]).check(&p("132")));
+        assert!(!NoRepeatedTriplet.check(&p("1111")));
+        assert!(NoRepeatedTriplet.check(&p("123412")) && !NoRepeatedTriplet.check(&p("1231234")));
+        assert!(DigitSum(10..=12).check(&p("1234")) && !DigitSum(10..=12).check(&p("99")));
+        assert!(SumModulo::even().check(&p("1234")) && SumModulo::odd().check(&p("124")));
+    }
+
+    #[test]
+    fn test_combinators() {
+        let small_or_even = Or(vec![Box::new(DigitSum(0..=3)), Box::new(SumModulo::even())]);
+        assert!(small_or_even.check(&p("111")) && small_or_even.check(&p("55")));
+        assert!(!small_or_even.check(&p("45")));
+        let no_pair = Not(Box::new(Runs::has_pair()));
+        assert!(no_pair.check(&p("1213")) && !no_pair.check(&p("1223")));
+        assert!(And(vec![]).check(&p("1")) && !Or(vec![]).check(&p("1")));
+
+        let nested = And(vec![
+            Box::new(part_b()),
+            Box::new(Not(Box::new(small_or_even))),
+        ]);
+        assert!(nested.check(&p("112344")) && !nested.check(&p("112345")));
+    }
+}
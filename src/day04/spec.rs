@@ -0,0 +1,446 @@
+//! A small language to write rules as text, for instance
+//!
+//! ```text
+//! len=6; nondecreasing; run(==2) >= 1; digits in 1..9
+//! ```
+//!
+//! Statements are separated by `;` or new lines and must all hold, `#` starts a comment.
+//! A statement is one of the rules below, combined with `and`, `or`, `not` and parentheses.
+//! Comparisons are `=`, `==`, `!=`, `<`, `<=`, `>`, `>=`, or `in A..B` with both ends included.
+//!
+//! - `len CMP N`: number of digits
+//! - `nondecreasing`: digits never go down
+//! - `pair`: some adjacent digits are the same
+//! - `run(CMP N) CMP M`: number of runs of the same digit whose length compares to `N`, at
+//!   least one when the count is left out
+//! - `digits in A..B` or `digits in A, B, ...`: allowed digits, up to 35
+//! - `no repeated triplets`: no three consecutive digits show up twice
+//! - `sum CMP N`, `sum even`, `sum odd`, `sum % M == R`: sum of the digits
+
+use super::rules::{
+    And, DigitSum, Digits, Length, NoRepeatedTriplet, NonDecreasing, Not, Or, Rule, Runs, SumModulo,
+};
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+struct Token<'a> {
+    text: &'a str,
+    /// Byte offset in the spec
+    at: usize,
+}
+
+const SYMBOLS: [&str; 13] = [
+    "==", "!=", "<=", ">=", "..", "=", "<", ">", "(", ")", ",", ";", "%",
+];
+
+fn tokenize(spec: &str) -> Result<Vec<Token<'_>>, String> {
+    let mut tokens = vec![];
+    let mut at = 0;
+    while let Some(c) = spec[at..].chars().next() {
+        let rest = &spec[at..];
+        let len = if c == '#' {
+            at += rest.find('\n').unwrap_or(rest.len());
+            continue;
+        } else if c == '\n' {
+            // Ends a statement, as `;` does
+            tokens.push(Token { text: ";", at });
+            1
+        } else if c.is_whitespace() {
+            c.len_utf8()
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            tokens.push(Token {
+                text: &rest[..len],
+                at,
+            });
+            len
+        } else if c.is_ascii_alphabetic() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            tokens.push(Token {
+                text: &rest[..len],
+                at,
+            });
+            len
+        } else {
+            let symbol = SYMBOLS.iter().find(|s| rest.starts_with(*s));
+            let symbol = symbol.ok_or_else(|| error(spec, at, &format!("unexpected {:?}", c)))?;
+            tokens.push(Token { text: symbol, at });
+            symbol.len()
+        };
+        at += len;
+    }
+    Ok(tokens)
+}
+
+/// Points at the error in its line of the spec
+fn error(spec: &str, at: usize, message: &str) -> String {
+    let start = spec[..at].rfind('\n').map_or(0, |i| i + 1);
+    let end = spec[at..].find('\n').map_or(spec.len(), |i| at + i);
+    let line = spec[..start].matches('\n').count() + 1;
+    let column = spec[start..at].chars().count();
+    format!(
+        "line {}, column {}: {}\n  {}\n  {}^",
+        line,
+        column + 1,
+        message,
+        &spec[start..end],
+        " ".repeat(column)
+    )
+}
+
+/// Largest lengths and counts of runs
+const COUNT: u64 = usize::MAX as u64;
+/// Largest sums of digits
+const SUM: u64 = u32::MAX as u64;
+/// Largest digit of the largest radix, 36
+const LARGEST_DIGIT: u64 = 35;
+
+/// Numbers compared to something
+#[derive(PartialEq, Debug, Copy, Clone)]
+enum Comparison {
+    Between(u64, u64),
+    Not(u64),
+}
+
+struct Parser<'a> {
+    spec: &'a str,
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(|t| t.text)
+    }
+
+    /// Error at the current token
+    fn error(&self, message: &str) -> String {
+        let at = self.tokens.get(self.pos).map_or(self.spec.len(), |t| t.at);
+        error(self.spec, at, message)
+    }
+
+    fn expected(&self, what: &str) -> String {
+        match self.peek() {
+            Some(found) => self.error(&format!("expected {}, found {:?}", what, found)),
+            None => self.error(&format!("expected {}, found the end", what)),
+        }
+    }
+
+    /// Moves past the token if it is `text`
+    fn eat(&mut self, text: &str) -> bool {
+        if self.peek() == Some(text) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), String> {
+        if self.eat(text) {
+            Ok(())
+        } else {
+            Err(self.expected(&format!("{:?}", text)))
+        }
+    }
+
+    /// A number up to `max`
+    fn number(&mut self, max: u64) -> Result<u64, String> {
+        match self.peek().map(str::parse) {
+            Some(Ok(n)) if n > max => Err(self.error(&format!("{} is more than {}", n, max))),
+            Some(Ok(n)) => {
+                self.pos += 1;
+                Ok(n)
+            }
+            _ => Err(self.expected("a number")),
+        }
+    }
+
+    /// `CMP N` or `in A..B`, for numbers up to `max`
+    fn comparison(&mut self, max: u64) -> Result<Comparison, String> {
+        let op = match self.peek() {
+            Some(op @ ("=" | "==" | "!=" | "<" | "<=" | ">" | ">=" | "in")) => op,
+            _ => return Err(self.expected("a comparison")),
+        };
+        self.pos += 1;
+        let before = self.pos;
+        if op == "in" {
+            let lo = self.number(max)?;
+            self.expect("..")?;
+            let hi = self.number(max)?;
+            if lo > hi {
+                self.pos = before;
+                return Err(self.error(&format!("{}..{} is empty", lo, hi)));
+            }
+            return Ok(Comparison::Between(lo, hi));
+        }
+        let n = self.number(max)?;
+        Ok(match op {
+            "=" | "==" => Comparison::Between(n, n),
+            "!=" => Comparison::Not(n),
+            "<" if n == 0 => {
+                self.pos = before;
+                return Err(self.error("nothing is below 0"));
+            }
+            "<" => Comparison::Between(0, n - 1),
+            "<=" => Comparison::Between(0, n),
+            ">" if n == max => {
+                self.pos = before;
+                return Err(self.error(&format!("nothing is above {}", max)));
+            }
+            ">" => Comparison::Between(n + 1, max),
+            _ => Comparison::Between(n, max),
+        })
+    }
+
+    /// `or` of `and` of possibly negated rules
+    fn rule(&mut self) -> Result<Box<dyn Rule>, String> {
+        let mut any = vec![self.all()?];
+        while self.eat("or") {
+            any.push(self.all()?);
+        }
+        Ok(if any.len() == 1 {
+            any.pop().unwrap()
+        } else {
+            Box::new(Or(any))
+        })
+    }
+
+    fn all(&mut self) -> Result<Box<dyn Rule>, String> {
+        let mut all = vec![self.unary()?];
+        while self.eat("and") {
+            all.push(self.unary()?);
+        }
+        Ok(if all.len() == 1 {
+            all.pop().unwrap()
+        } else {
+            Box::new(And(all))
+        })
+    }
+
+    fn unary(&mut self) -> Result<Box<dyn Rule>, String> {
+        if self.eat("not") {
+            return Ok(Box::new(Not(self.unary()?)));
+        }
+        if self.eat("(") {
+            let rule = self.rule()?;
+            self.expect(")")?;
+            return Ok(rule);
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Box<dyn Rule>, String> {
+        let word = self.peek().ok_or_else(|| self.expected("a rule"))?;
+        let start = self.pos;
+        self.pos += 1;
+        Ok(match word {
+            "nondecreasing" => Box::new(NonDecreasing),
+            "pair" => Box::new(Runs::has_pair()),
+            "len" => compare(self.comparison(COUNT)?, |lo, hi| {
+                Box::new(Length(lo as usize..=hi as usize))
+            }),
+            "run" => {
+                self.expect("(")?;
+                let at = self.pos;
+                let length = match self.comparison(COUNT)? {
+                    Comparison::Between(lo, hi) => lo as usize..=hi as usize,
+                    Comparison::Not(_) => {
+                        self.pos = at;
+                        return Err(self.error("a run length cannot be compared with !="));
+                    }
+                };
+                self.expect(")")?;
+                let count = match self.peek() {
+                    Some(";" | ")" | "and" | "or") | None => Comparison::Between(1, COUNT),
+                    _ => self.comparison(COUNT)?,
+                };
+                compare(count, |lo, hi| {
+                    Box::new(Runs {
+                        length: length.clone(),
+                        count: lo as usize..=hi as usize,
+                    })
+                })
+            }
+            "digits" => {
+                self.expect("in")?;
+                let at = self.pos;
+                let first = self.number(LARGEST_DIGIT)?;
+                let digits = if self.eat("..") {
+                    let last = self.number(LARGEST_DIGIT)?;
+                    if first > last {
+                        self.pos = at;
+                        return Err(self.error(&format!("{}..{} is empty", first, last)));
+                    }
+                    (first..=last).collect()
+                } else {
+                    let mut digits = vec![first];
+                    while self.eat(",") {
+                        digits.push(self.number(LARGEST_DIGIT)?);
+                    }
+                    digits
+                };
+                Box::new(Digits(digits.into_iter().map(|d| d as u32).collect()))
+            }
+            "no" => {
+                self.expect("repeated")?;
+                if !self.eat("triplets") {
+                    self.expect("triplet")?;
+                }
+                Box::new(NoRepeatedTriplet)
+            }
+            "sum" => {
+                if self.eat("even") {
+                    Box::new(SumModulo::even())
+                } else if self.eat("odd") {
+                    Box::new(SumModulo::odd())
+                } else if self.eat("%") {
+                    let modulus = self.number(SUM)?;
+                    if modulus == 0 {
+                        self.pos -= 1;
+                        return Err(self.error("the modulus must not be 0"));
+                    }
+                    if !self.eat("==") {
+                        self.expect("=")?;
+                    }
+                    let remainder = self.number(SUM)?;
+                    Box::new(SumModulo {
+                        modulus: modulus as u32,
+                        remainder: remainder as u32,
+                    })
+                } else {
+                    compare(self.comparison(SUM)?, |lo, hi| {
+                        Box::new(DigitSum(lo as u32..=hi as u32))
+                    })
+                }
+            }
+            _ => {
+                self.pos = start;
+                return Err(self.expected("a rule"));
+            }
+        })
+    }
+}
+
+/// The rule for numbers in a range, built by `between`
+fn compare<F>(comparison: Comparison, between: F) -> Box<dyn Rule>
+where
+    F: Fn(u64, u64) -> Box<dyn Rule>,
+{
+    match comparison {
+        Comparison::Between(lo, hi) => between(lo, hi),
+        Comparison::Not(n) => Box::new(Not(between(n, n))),
+    }
+}
+
+/// Parses a spec into the rule of all its statements
+pub fn parse(spec: &str) -> Result<And, String> {
+    let mut parser = Parser {
+        spec,
+        tokens: tokenize(spec)?,
+        pos: 0,
+    };
+    let mut statements = vec![];
+    loop {
+        while parser.eat(";") {}
+        if parser.peek().is_none() {
+            return Ok(And(statements));
+        }
+        statements.push(parser.rule()?);
+        if parser.peek().is_some() {
+            parser.expect(";")?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{count, rules, Password};
+    use super::*;
+
+    fn check(spec: &str, s: &str) -> bool {
+        parse(spec)
+            .unwrap()
+            .check(&Password::from_s(s, 10).unwrap())
+    }
+
+    #[test]
+    fn test_parse() {
+        let spec = "len=6; nondecreasing; run(==2) >= 1; digits in 1..9";
+        assert!(check(spec, "112233") && check(spec, "111122"));
+        assert!(!check(spec, "123444") && !check(spec, "011233") && !check(spec, "11223"));
+        let rule = parse(spec).unwrap();
+        let (part_b, range) = (rules::part_b(), 111_111..=999_999);
+        assert_eq!(
+            count(range.clone(), 6, 10, &rule),
+            count(range, 6, 10, &part_b)
+        );
+
+        let spec = "# puzzle, first part\npair\n\n nondecreasing ;";
+        assert_eq!(
+            count(156_218..652_527, 6, 10, &parse(spec).unwrap()),
+            Ok(1694)
+        );
+
+        assert!(check("run(>=3) = 0 and sum even", "1122"));
+        assert!(check("not pair or sum % 3 == 1", "1234"));
+        assert!(!check("(pair or sum > 20) and len != 4", "1123"));
+        assert!(check(
+            "digits in 1, 3, 5; sum in 7..9; no repeated triplets",
+            "1331"
+        ));
+        assert!(!check("no repeated triplet", "121212"));
+        assert!(check("sum odd; len <= 3; run(in 1..1) < 2", "113"));
+        assert!(check("", "1"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            parse("len=6; run(==2) >= x").err().unwrap(),
+            "line 1, column 20: expected a number, found \"x\"\n  len=6; run(==2) >= x\n                     ^"
+        );
+        assert_eq!(
+            parse("pair\nsorted").err().unwrap(),
+            "line 2, column 1: expected a rule, found \"sorted\"\n  sorted\n  ^"
+        );
+        assert_eq!(
+            parse("len > 2 len < 5").err().unwrap(),
+            "line 1, column 9: expected \";\", found \"len\"\n  len > 2 len < 5\n          ^"
+        );
+        assert_eq!(
+            parse("digits in 1..40").err().unwrap(),
+            "line 1, column 14: 40 is more than 35\n  digits in 1..40\n               ^"
+        );
+        assert_eq!(
+            parse("(pair").err().unwrap(),
+            "line 1, column 6: expected \")\", found the end\n  (pair\n       ^"
+        );
+        for (spec, message) in [
+            ("len ~ 3", "unexpected '~'"),
+            ("sum % 0 == 1", "the modulus must not be 0"),
+            ("run(!=2)", "a run length cannot be compared with !="),
+            ("len < 0", "nothing is below 0"),
+            ("no triplets", "expected \"repeated\""),
+            (
+                "len > 18446744073709551615",
+                "nothing is above 18446744073709551615",
+            ),
+            (
+                "sum % 4294967297 == 1",
+                "4294967297 is more than 4294967295",
+            ),
+            ("sum < 4294967296", "4294967296 is more than 4294967295"),
+            ("digits in 0..36", "36 is more than 35"),
+            ("digits in 1, 99999999999", "99999999999 is more than 35"),
+            ("digits in 5..2", "5..2 is empty"),
+            ("run(in 3..2)", "3..2 is empty"),
+        ] {
+            let e = parse(spec).err().unwrap();
+            assert!(e.contains(message), "{}: {}", spec, e);
+        }
+    }
+}
@@ -0,0 +1,425 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+pub mod program;
+pub mod robot;
+pub mod screen;
+
+/// Why the machine stopped running.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Outcome {
+    /// Opcode 99 was reached, running again is a no-op.
+    Halted,
+    /// Opcode 3 was reached with an empty input queue, push some input and run again.
+    NeedsInput,
+    /// Opcode 4 produced a value, run again to continue.
+    Output(i64),
+    /// A limit of the budget was hit before executing the instruction at `ip`. The machine is
+    /// left untouched, raise the budget and run again to continue.
+    BudgetExceeded(Exceeded),
+}
+
+/// Limits on how much work a machine may do, `None` means unlimited.
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct Budget {
+    /// Instructions executed since the machine was created
+    pub max_instructions: Option<u64>,
+    pub deadline: Option<Instant>,
+    /// Number of memory cells, including the ones the program grows into
+    pub max_memory: Option<usize>,
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Limit {
+    Instructions,
+    Deadline,
+    Memory,
+}
+
+/// Which limit was hit and the state of the machine at that point.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Exceeded {
+    pub limit: Limit,
+    pub ip: usize,
+    pub instructions: u64,
+    pub memory: usize,
+}
+
+/// An Intcode computer: memory, instruction pointer, relative base and pending input.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Machine {
+    memory: Vec<i64>,
+    ip: usize,
+    relative_base: i64,
+    input: VecDeque<i64>,
+    budget: Budget,
+    instructions: u64,
+}
+
+/// Parses a comma separated program
+/// ```
+/// assert_eq!(aoc2019::intcode::parse("1,-2,99"), vec![1, -2, 99])
+/// ```
+pub fn parse(s: &str) -> Vec<i64> {
+    s.trim()
+        .split(',')
+        .map(|s| -> i64 { s.trim().parse().unwrap() })
+        .collect()
+}
+
+impl Machine {
+    pub fn new(memory: Vec<i64>) -> Machine {
+        Machine {
+            memory,
+            ip: 0,
+            relative_base: 0,
+            input: VecDeque::new(),
+            budget: Budget::default(),
+            instructions: 0,
+        }
+    }
+
+    pub fn set_budget(&mut self, budget: Budget) {
+        self.budget = budget;
+    }
+
+    /// Number of instructions executed so far
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    pub fn push_input(&mut self, value: i64) {
+        self.input.push_back(value);
+    }
+
+    pub fn memory(&self) -> &[i64] {
+        &self.memory
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// Reads a cell, memory beyond the program is zero.
+    pub fn get(&self, addr: usize) -> i64 {
+        self.memory.get(addr).copied().unwrap_or(0)
+    }
+
+    /// Overwrites a cell, growing memory if needed. Fails when memory cannot grow that far.
+    pub fn patch(&mut self, addr: usize, value: i64) -> Result<(), &'static str> {
+        if addr >= self.memory.len() {
+            let len = addr.checked_add(1).ok_or("Address out of range")?;
+            self.memory
+                .try_reserve(len - self.memory.len())
+                .map_err(|_| "Address out of range")?;
+            self.memory.resize(len, 0);
+        }
+        self.memory[addr] = value;
+        Ok(())
+    }
+
+    pub fn patch_all(&mut self, patches: &[(usize, i64)]) -> Result<(), &'static str> {
+        for (addr, value) in patches {
+            self.patch(*addr, *value)?;
+        }
+        Ok(())
+    }
+
+    /// Runs until the machine halts, needs input, outputs a value or exceeds its budget.
+    pub fn run(&mut self) -> Result<Outcome, &'static str> {
+        loop {
+            if let Some(limit) = self.over_budget() {
+                return Ok(self.exceeded(limit));
+            }
+            if let Some(outcome) = self.step()? {
+                return Ok(outcome);
+            }
+        }
+    }
+
+    fn over_budget(&self) -> Option<Limit> {
+        if let Some(max) = self.budget.max_instructions {
+            if self.instructions >= max {
+                return Some(Limit::Instructions);
+            }
+        }
+        // Reading the clock on every instruction would dominate the run time
+        if let Some(deadline) = self.budget.deadline {
+            if self.instructions % 1024 == 0 && Instant::now() >= deadline {
+                return Some(Limit::Deadline);
+            }
+        }
+        None
+    }
+
+    fn exceeded(&self, limit: Limit) -> Outcome {
+        Outcome::BudgetExceeded(Exceeded {
+            limit,
+            ip: self.ip,
+            instructions: self.instructions,
+            memory: self.memory.len(),
+        })
+    }
+
+    /// Runs to completion with the given input, collecting every output.
+    pub fn run_to_halt(&mut self, input: &[i64]) -> Result<Vec<i64>, &'static str> {
+        self.input.extend(input);
+        let mut output = vec![];
+        loop {
+            match self.run()? {
+                Outcome::Halted => return Ok(output),
+                Outcome::NeedsInput => return Err("Input exhausted"),
+                Outcome::Output(v) => output.push(v),
+                Outcome::BudgetExceeded(_) => return Err("Budget exceeded"),
+            }
+        }
+    }
+
+    /// Executes one instruction, returns an outcome if it stops the machine.
+    fn step(&mut self) -> Result<Option<Outcome>, &'static str> {
+        let instruction = self.get(self.ip);
+        if self.would_grow_past_budget(instruction)? {
+            return Ok(Some(self.exceeded(Limit::Memory)));
+        }
+        if instruction < 0 {
+            return Err("Illegal opcode");
+        }
+        match instruction % 100 {
+            1 | 2 => {
+                let a = self.param(1)?;
+                let b = self.param(2)?;
+                let value = if instruction % 100 == 1 {
+                    a.checked_add(b)
+                } else {
+                    a.checked_mul(b)
+                };
+                self.store(3, value.ok_or("Overflow")?)?;
+                self.ip += 4;
+            }
+            3 => match self.input.pop_front() {
+                Some(value) => {
+                    self.store(1, value)?;
+                    self.ip += 2;
+                }
+                None => return Ok(Some(Outcome::NeedsInput)),
+            },
+            4 => {
+                let value = self.param(1)?;
+                self.ip += 2;
+                self.instructions += 1;
+                return Ok(Some(Outcome::Output(value)));
+            }
+            5 | 6 => {
+                let test = self.param(1)?;
+                if (test != 0) == (instruction % 100 == 5) {
+                    self.ip = to_addr(self.param(2)?)?;
+                } else {
+                    self.ip += 3;
+                }
+            }
+            7 | 8 => {
+                let a = self.param(1)?;
+                let b = self.param(2)?;
+                let value = if instruction % 100 == 7 {
+                    a < b
+                } else {
+                    a == b
+                };
+                self.store(3, value as i64)?;
+                self.ip += 4;
+            }
+            9 => {
+                self.relative_base = self
+                    .relative_base
+                    .checked_add(self.param(1)?)
+                    .ok_or("Overflow")?;
+                self.ip += 2;
+            }
+            99 => return Ok(Some(Outcome::Halted)),
+            _ => return Err("Illegal opcode"),
+        }
+        self.instructions += 1;
+        Ok(None)
+    }
+
+    /// Whether the instruction writes to a cell beyond the memory budget.
+    fn would_grow_past_budget(&self, instruction: i64) -> Result<bool, &'static str> {
+        let max = match self.budget.max_memory {
+            Some(max) => max,
+            None => return Ok(false),
+        };
+        let target = match instruction % 100 {
+            1 | 2 | 7 | 8 => self.addr(3)?,
+            3 if !self.input.is_empty() => self.addr(1)?,
+            _ => return Ok(false),
+        };
+        Ok(target >= max.max(self.memory.len()))
+    }
+
+    /// Mode of the n-th parameter (1 based) of the current instruction.
+    fn mode(&self, n: u32) -> i64 {
+        self.get(self.ip) / 10_i64.pow(n + 1) % 10
+    }
+
+    /// Address the n-th parameter points to, for position and relative modes.
+    fn addr(&self, n: u32) -> Result<usize, &'static str> {
+        let raw = self.get(self.ip + n as usize);
+        match self.mode(n) {
+            0 => to_addr(raw),
+            2 => to_addr(self.relative_base.checked_add(raw).ok_or("Overflow")?),
+            _ => Err("Illegal parameter mode"),
+        }
+    }
+
+    fn param(&self, n: u32) -> Result<i64, &'static str> {
+        match self.mode(n) {
+            1 => Ok(self.get(self.ip + n as usize)),
+            _ => Ok(self.get(self.addr(n)?)),
+        }
+    }
+
+    fn store(&mut self, n: u32, value: i64) -> Result<(), &'static str> {
+        let addr = self.addr(n)?;
+        self.patch(addr, value)
+    }
+}
+
+fn to_addr(v: i64) -> Result<usize, &'static str> {
+    if v < 0 {
+        return Err("Negative address");
+    }
+    Ok(v as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(s: &str) -> Vec<i64> {
+        let mut m = Machine::new(parse(s));
+        m.run_to_halt(&[]).unwrap();
+        m.memory().to_vec()
+    }
+
+    #[test]
+    fn test_add_mul() {
+        assert_eq!(run("1,0,0,0,99"), vec![2, 0, 0, 0, 99]);
+        assert_eq!(run("2,4,4,5,99,0"), vec![2, 4, 4, 5, 99, 9801]);
+        assert_eq!(run("1002,4,3,4,33"), vec![1002, 4, 3, 4, 99]);
+    }
+
+    #[test]
+    fn test_io_and_jumps() {
+        // Outputs 999 if the input is below 8, 1000 if equal, 1001 if above
+        let program = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,\
+                       1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,\
+                       999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+        for (input, expected) in [(7, 999), (8, 1000), (9, 1001)] {
+            let mut m = Machine::new(parse(program));
+            assert_eq!(m.run_to_halt(&[input]).unwrap(), vec![expected]);
+        }
+    }
+
+    #[test]
+    fn test_relative_base() {
+        let quine = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+        let mut m = Machine::new(parse(quine));
+        assert_eq!(m.run_to_halt(&[]).unwrap(), parse(quine));
+    }
+
+    #[test]
+    fn test_needs_input() {
+        let mut m = Machine::new(parse("3,0,4,0,99"));
+        assert_eq!(m.run(), Ok(Outcome::NeedsInput));
+        m.push_input(42);
+        assert_eq!(m.run(), Ok(Outcome::Output(42)));
+        assert_eq!(m.run(), Ok(Outcome::Halted));
+        assert_eq!(m.run(), Ok(Outcome::Halted));
+    }
+
+    #[test]
+    fn test_patch() {
+        let mut m = Machine::new(parse("1,0,0,0,99"));
+        m.patch(1, 4).unwrap();
+        m.patch_all(&[(2, 4), (6, 7)]).unwrap();
+        assert_eq!(m.memory(), &[1, 4, 4, 0, 99, 0, 7]);
+        m.run_to_halt(&[]).unwrap();
+        assert_eq!(m.get(0), 198);
+        assert_eq!(m.get(100), 0);
+        assert_eq!(m.patch(usize::MAX, 1), Err("Address out of range"));
+        assert_eq!(m.patch(1 << 62, 1), Err("Address out of range"));
+    }
+
+    #[test]
+    fn test_budget() {
+        let mut looping = Machine::new(parse("1105,1,0"));
+        looping.set_budget(Budget {
+            max_instructions: Some(10),
+            ..Default::default()
+        });
+        let exceeded = Exceeded {
+            limit: Limit::Instructions,
+            ip: 0,
+            instructions: 10,
+            memory: 3,
+        };
+        assert_eq!(looping.run(), Ok(Outcome::BudgetExceeded(exceeded)));
+        assert_eq!(looping.run_to_halt(&[]), Err("Budget exceeded"));
+
+        looping.set_budget(Budget {
+            deadline: Some(Instant::now()),
+            ..Default::default()
+        });
+        match looping.run() {
+            Ok(Outcome::BudgetExceeded(e)) => assert_eq!(e.limit, Limit::Deadline),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // Writes to cell 100 then halts
+        let mut growing = Machine::new(parse("1101,1,1,100,99"));
+        growing.set_budget(Budget {
+            max_memory: Some(50),
+            ..Default::default()
+        });
+        match growing.run() {
+            Ok(Outcome::BudgetExceeded(e)) => {
+                assert_eq!((e.limit, e.ip, e.memory), (Limit::Memory, 0, 5))
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        growing.set_budget(Budget::default());
+        assert_eq!(growing.run(), Ok(Outcome::Halted));
+        assert_eq!(growing.get(100), 2);
+        assert_eq!(growing.instructions(), 1);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Machine::new(parse("42")).run(), Err("Illegal opcode"));
+        assert_eq!(
+            Machine::new(parse("1,-1,0,0,99")).run(),
+            Err("Negative address")
+        );
+        assert_eq!(
+            Machine::new(parse("3,0,99")).run_to_halt(&[]),
+            Err("Input exhausted")
+        );
+        for program in [
+            "1102,9223372036854775807,2,0,99",
+            "1101,9223372036854775807,1,0,99",
+            "109,9223372036854775807,209,1,99",
+            "109,9223372036854775807,109,1,99",
+        ] {
+            assert_eq!(
+                Machine::new(parse(program)).run(),
+                Err("Overflow"),
+                "{}",
+                program
+            );
+        }
+        assert_eq!(
+            Machine::new(parse("1101,1,1,4611686018427387904,99")).run(),
+            Err("Address out of range")
+        );
+    }
+}
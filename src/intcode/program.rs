@@ -0,0 +1,241 @@
+//! Program files with an optional metadata header.
+//!
+//! A plain comma separated list is a valid program. Before it, `#` lines can describe the
+//! program:
+//!
+//! ```text
+//! # name: 1202 Program Alarm
+//! # input: none
+//! # output: cell 0 once halted
+//! # symbol: noun=1, verb=2
+//! # patch: noun=12, verb=2
+//! 1,0,0,3,1,1,2,3,99
+//! ```
+//!
+//! Patches are applied when a machine is built from the program, they can name a cell by
+//! address or by symbol.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::{Error, Formatter};
+
+use super::Machine;
+
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Program {
+    pub name: Option<String>,
+    /// What the program expects to read
+    pub input: Option<String>,
+    /// What the program produces
+    pub output: Option<String>,
+    /// Named memory cells
+    pub symbols: BTreeMap<String, usize>,
+    /// Cells to overwrite before running
+    pub patches: Vec<(usize, i64)>,
+    pub code: Vec<i64>,
+}
+
+impl Program {
+    pub fn new(code: Vec<i64>) -> Program {
+        Program {
+            code,
+            ..Default::default()
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Program, String> {
+        let mut program = Program::default();
+        let mut patches = vec![];
+        let mut code = String::new();
+        for (n, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let header = match line.strip_prefix('#') {
+                Some(h) => h,
+                None => {
+                    // Lines of code are separate cells even without a trailing comma
+                    if !code.is_empty() && !code.ends_with(',') {
+                        code.push(',');
+                    }
+                    code.push_str(line);
+                    continue;
+                }
+            };
+            if !code.is_empty() {
+                return Err(format!("line {}: header after the code", n + 1));
+            }
+            let (key, value) = match header.find(':') {
+                Some(i) => (header[..i].trim(), header[i + 1..].trim()),
+                None => continue,
+            };
+            match key {
+                "name" => program.name = Some(value.to_string()),
+                "input" => program.input = Some(value.to_string()),
+                "output" => program.output = Some(value.to_string()),
+                "symbol" => {
+                    for (name, addr) in assignments(value, n)? {
+                        let addr = addr
+                            .parse()
+                            .map_err(|_| format!("line {}: bad address {:?}", n + 1, addr))?;
+                        program.symbols.insert(name.to_string(), addr);
+                    }
+                }
+                "patch" => {
+                    for (cell, value) in assignments(value, n)? {
+                        let value: i64 = value
+                            .parse()
+                            .map_err(|_| format!("line {}: bad value {:?}", n + 1, value))?;
+                        patches.push((n, cell.to_string(), value));
+                    }
+                }
+                _ => return Err(format!("line {}: unknown header {:?}", n + 1, key)),
+            }
+        }
+        for (n, cell, value) in patches {
+            let addr = program
+                .address(&cell)
+                .ok_or_else(|| format!("line {}: unknown cell {:?}", n + 1, cell))?;
+            program.patches.push((addr, value));
+        }
+        program.code = code
+            .split(',')
+            .map(|s| s.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("bad code: {}", e))?;
+        Ok(program)
+    }
+
+    /// Resolves a cell given either as a symbol or an address.
+    pub fn address(&self, cell: &str) -> Option<usize> {
+        match self.symbols.get(cell) {
+            Some(addr) => Some(*addr),
+            None => cell.parse().ok(),
+        }
+    }
+
+    /// Parses a `cell=value` patch, the cell being a symbol or an address.
+    pub fn parse_patch(&self, s: &str) -> Result<(usize, i64), String> {
+        let mut parts = s.splitn(2, '=');
+        let (cell, value) = match (parts.next(), parts.next()) {
+            (Some(k), Some(v)) => (k.trim(), v.trim()),
+            _ => return Err(format!("expected cell=value, got {:?}", s)),
+        };
+        let addr = self
+            .address(cell)
+            .ok_or_else(|| format!("unknown cell {:?}", cell))?;
+        let value = value
+            .parse()
+            .map_err(|_| format!("bad value {:?}", value))?;
+        Ok((addr, value))
+    }
+
+    /// A machine loaded with the code, patches applied.
+    pub fn machine(&self) -> Result<Machine, &'static str> {
+        let mut machine = Machine::new(self.code.clone());
+        machine.patch_all(&self.patches)?;
+        Ok(machine)
+    }
+}
+
+/// Splits `a=1, b=2` into pairs
+fn assignments(s: &str, n: usize) -> Result<Vec<(&str, &str)>, String> {
+    s.split(',')
+        .map(|a| {
+            let mut parts = a.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(k), Some(v)) => Ok((k.trim(), v.trim())),
+                _ => Err(format!("line {}: expected cell=value, got {:?}", n + 1, a)),
+            }
+        })
+        .collect()
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        for (key, value) in &[
+            ("name", &self.name),
+            ("input", &self.input),
+            ("output", &self.output),
+        ] {
+            if let Some(v) = value {
+                writeln!(f, "# {}: {}", key, v)?;
+            }
+        }
+        if !self.symbols.is_empty() {
+            let symbols: Vec<String> = self
+                .symbols
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect();
+            writeln!(f, "# symbol: {}", symbols.join(", "))?;
+        }
+        if !self.patches.is_empty() {
+            let patches: Vec<String> = self
+                .patches
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect();
+            writeln!(f, "# patch: {}", patches.join(", "))?;
+        }
+        let code: Vec<String> = self.code.iter().map(|v| v.to_string()).collect();
+        write!(f, "{}", code.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALARM: &str = "# name: 1202 Program Alarm
+# output: cell 0
+# symbol: noun=1, verb=2
+# patch: noun=12, 2=2
+1,0,0,3,
+1,1,2,3,99";
+
+    #[test]
+    fn test_plain() {
+        let p = Program::parse("1,0,0,0,99").unwrap();
+        assert_eq!(p, Program::new(vec![1, 0, 0, 0, 99]));
+        assert_eq!(p.to_string(), "1,0,0,0,99");
+        let lines = Program::parse("1,0,\n0,0\n99\n").unwrap();
+        assert_eq!(lines.code, vec![1, 0, 0, 0, 99]);
+    }
+
+    #[test]
+    fn test_header() {
+        let p = Program::parse(ALARM).unwrap();
+        assert_eq!(p.name, Some(String::from("1202 Program Alarm")));
+        assert_eq!(p.input, None);
+        assert_eq!(p.address("verb"), Some(2));
+        assert_eq!(p.address("7"), Some(7));
+        assert_eq!(p.patches, vec![(1, 12), (2, 2)]);
+        assert_eq!(
+            p.machine().unwrap().memory(),
+            &[1, 12, 2, 3, 1, 1, 2, 3, 99]
+        );
+        assert_eq!(p.parse_patch("noun=5"), Ok((1, 5)));
+        assert_eq!(p.parse_patch("3=-1"), Ok((3, -1)));
+        assert!(p.parse_patch("foo=1").is_err());
+        assert_eq!(Program::parse(&p.to_string()), Ok(p));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Program::parse("# patch: foo=1\n99"),
+            Err(String::from("line 1: unknown cell \"foo\""))
+        );
+        assert_eq!(
+            Program::parse("# colour: red\n99"),
+            Err(String::from("line 1: unknown header \"colour\""))
+        );
+        assert_eq!(
+            Program::parse("99\n# name: late"),
+            Err(String::from("line 2: header after the code"))
+        );
+        assert!(Program::parse("1,x,3").is_err());
+    }
+}
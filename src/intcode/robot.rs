@@ -0,0 +1,417 @@
+//! Drivers for programs that steer a robot around a grid: painting robots, repair droids
+//! exploring a maze and drones probing a tractor beam.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::screen::Screen;
+use super::{Machine, Outcome};
+
+/// Position on the grid, `y` grows southward like on the screen.
+pub type Pos = (i64, i64);
+
+/// Status a droid reports when it bumps into a wall, anything else means it moved.
+pub const WALL: i64 = 0;
+
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone)]
+pub enum Heading {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Heading {
+    pub const ALL: [Heading; 4] = [Heading::North, Heading::East, Heading::South, Heading::West];
+
+    pub fn turn_left(self) -> Heading {
+        use Heading::*;
+        match self {
+            North => West,
+            West => South,
+            South => East,
+            East => North,
+        }
+    }
+
+    pub fn turn_right(self) -> Heading {
+        self.turn_left().reverse()
+    }
+
+    pub fn reverse(self) -> Heading {
+        self.turn_left().turn_left()
+    }
+
+    pub fn delta(self) -> Pos {
+        use Heading::*;
+        match self {
+            North => (0, -1),
+            East => (1, 0),
+            South => (0, 1),
+            West => (-1, 0),
+        }
+    }
+
+    /// Movement command understood by the droids: north (1), south (2), west (3), east (4).
+    pub fn command(self) -> i64 {
+        use Heading::*;
+        match self {
+            North => 1,
+            South => 2,
+            West => 3,
+            East => 4,
+        }
+    }
+
+    fn apply(self, pos: Pos) -> Pos {
+        let (dx, dy) = self.delta();
+        (pos.0 + dx, pos.1 + dy)
+    }
+
+    fn symbol(self) -> char {
+        use Heading::*;
+        match self {
+            North => '^',
+            East => '>',
+            South => 'v',
+            West => '<',
+        }
+    }
+}
+
+/// A robot driven by an Intcode program, remembering every tile it has seen.
+#[derive(Debug, Clone)]
+pub struct GridRobot {
+    machine: Machine,
+    pos: Pos,
+    heading: Heading,
+    map: Screen,
+}
+
+impl GridRobot {
+    pub fn new(machine: Machine) -> GridRobot {
+        GridRobot {
+            machine,
+            pos: (0, 0),
+            heading: Heading::North,
+            map: Screen::new(),
+        }
+    }
+
+    pub fn pos(&self) -> Pos {
+        self.pos
+    }
+
+    pub fn heading(&self) -> Heading {
+        self.heading
+    }
+
+    pub fn map(&self) -> &Screen {
+        &self.map
+    }
+
+    pub fn tile(&self, pos: Pos) -> Option<i64> {
+        self.map.get(pos.0, pos.1)
+    }
+
+    pub fn set_tile(&mut self, pos: Pos, tile: i64) {
+        self.map.set(pos.0, pos.1, tile)
+    }
+
+    /// Painting protocol: the program reads the colour under the robot, outputs the colour to
+    /// paint and a turn (0 left, 1 right), then the robot moves forward one panel.
+    /// Returns how many panels were painted at least once.
+    pub fn paint(&mut self) -> Result<usize, &'static str> {
+        let mut painted = HashSet::new();
+        let mut pending = vec![];
+        loop {
+            match self.machine.run()? {
+                Outcome::Halted => return Ok(painted.len()),
+                Outcome::BudgetExceeded(_) => return Err("Budget exceeded"),
+                Outcome::NeedsInput => {
+                    let colour = self.tile(self.pos).unwrap_or(0);
+                    self.machine.push_input(colour);
+                }
+                Outcome::Output(v) => {
+                    pending.push(v);
+                    if pending.len() == 2 {
+                        self.set_tile(self.pos, pending[0]);
+                        painted.insert(self.pos);
+                        self.heading = match pending[1] {
+                            0 => self.heading.turn_left(),
+                            1 => self.heading.turn_right(),
+                            _ => return Err("Illegal turn"),
+                        };
+                        self.pos = self.heading.apply(self.pos);
+                        pending.clear();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Movement protocol: sends a movement command and reads back a status. The status is
+    /// recorded on the map at the target position, the robot only moves if it is not a wall.
+    pub fn step(&mut self, heading: Heading) -> Result<i64, &'static str> {
+        if self.tile(self.pos).is_none() {
+            self.set_tile(self.pos, 1);
+        }
+        self.machine.push_input(heading.command());
+        let status = match self.machine.run()? {
+            Outcome::Output(v) => v,
+            _ => return Err("Expected a status"),
+        };
+        let target = heading.apply(self.pos);
+        self.set_tile(target, status);
+        self.heading = heading;
+        if status != WALL {
+            self.pos = target;
+        }
+        Ok(status)
+    }
+
+    /// Replays a path, failing if a wall is in the way.
+    pub fn follow(&mut self, path: &[Heading]) -> Result<(), &'static str> {
+        for h in path {
+            if self.step(*h)? == WALL {
+                return Err("Blocked by a wall");
+            }
+        }
+        Ok(())
+    }
+
+    /// Shortest path over the known map, only walking on tiles that are not walls.
+    pub fn shortest_path(&self, from: Pos, to: Pos) -> Option<Vec<Heading>> {
+        let mut previous = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(from);
+        previous.insert(from, None);
+        while let Some(pos) = queue.pop_front() {
+            if pos == to {
+                let mut path = vec![];
+                let mut cur = pos;
+                while let Some(Some((prev, h))) = previous.get(&cur) {
+                    path.push(*h);
+                    cur = *prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for h in Heading::ALL.iter() {
+                let next = h.apply(pos);
+                match self.tile(next) {
+                    Some(t) if t != WALL && !previous.contains_key(&next) => {
+                        previous.insert(next, Some((pos, *h)));
+                        queue.push_back(next);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        None
+    }
+
+    /// Renders the known map with the robot drawn as an arrow.
+    pub fn render<F>(&self, palette: F) -> String
+    where
+        F: Fn(i64) -> char,
+    {
+        let mut map = self.map.clone();
+        map.set(self.pos.0, self.pos.1, i64::MIN);
+        let robot = self.heading.symbol();
+        map.render(|t| if t == i64::MIN { robot } else { palette(t) })
+    }
+}
+
+/// A way of mapping out the area around a robot using the movement protocol.
+pub trait Strategy {
+    fn explore(&mut self, robot: &mut GridRobot) -> Result<(), &'static str>;
+}
+
+/// Walks every reachable tile, backtracking when stuck, and ends back at the start.
+pub struct DepthFirst;
+
+impl Strategy for DepthFirst {
+    fn explore(&mut self, robot: &mut GridRobot) -> Result<(), &'static str> {
+        let mut path: Vec<Heading> = vec![];
+        loop {
+            let unknown = Heading::ALL
+                .iter()
+                .find(|h| robot.tile(h.apply(robot.pos)).is_none());
+            match unknown {
+                Some(h) => {
+                    if robot.step(*h)? != WALL {
+                        path.push(*h);
+                    }
+                }
+                None => match path.pop() {
+                    Some(h) => {
+                        robot.step(h.reverse())?;
+                    }
+                    None => return Ok(()),
+                },
+            }
+        }
+    }
+}
+
+/// Explores in rings of increasing distance by cloning the machine at every tile, so the
+/// first time a tile is found its distance from the start is the shortest one. The robot
+/// itself does not move, only its map is filled in.
+#[derive(Default)]
+pub struct BreadthFirst {
+    /// Stop as soon as a tile with this status is found
+    pub target: Option<i64>,
+    /// Position and distance of the target once found
+    pub found: Option<(Pos, usize)>,
+}
+
+impl Strategy for BreadthFirst {
+    fn explore(&mut self, robot: &mut GridRobot) -> Result<(), &'static str> {
+        if robot.tile(robot.pos).is_none() {
+            robot.set_tile(robot.pos, 1);
+        }
+        let mut queue = VecDeque::new();
+        queue.push_back((robot.machine.clone(), robot.pos, 0));
+        while let Some((machine, pos, distance)) = queue.pop_front() {
+            for h in Heading::ALL.iter() {
+                let next = h.apply(pos);
+                if robot.tile(next).is_some() {
+                    continue;
+                }
+                let mut probe = GridRobot {
+                    machine: machine.clone(),
+                    pos,
+                    heading: *h,
+                    map: Screen::new(),
+                };
+                let status = probe.step(*h)?;
+                robot.set_tile(next, status);
+                if Some(status) == self.target {
+                    self.found = Some((next, distance + 1));
+                    return Ok(());
+                }
+                if status != WALL {
+                    queue.push_back((probe.machine, next, distance + 1));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Drone protocol: a fresh copy of the program reads a position and outputs what is there.
+pub fn probe(program: &Machine, pos: Pos) -> Result<i64, &'static str> {
+    let mut machine = program.clone();
+    match machine.run_to_halt(&[pos.0, pos.1])?.first() {
+        Some(v) => Ok(*v),
+        None => Err("Expected an output"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse;
+    use super::*;
+
+    /// Intcode droid walking a maze laid out in memory, 8 cells wide, starting at (1, 1).
+    /// `#` is a wall (0), `.` is open (1) and `O` is the target (2).
+    fn maze_droid(rows: &[&str]) -> Machine {
+        let mut program = parse(
+            "3,1002,1001,1000,0,1003,1001,1001,0,1004,\
+             1008,1002,1,1005,1002,1005,-1,1005,1,1004,1005,1004,\
+             1008,1002,2,1005,1,1004,1005,1004,\
+             1008,1002,3,1005,1002,1005,-1,1005,1,1003,1005,1003,\
+             1008,1002,4,1005,1,1003,1005,1003,\
+             1002,1004,8,1006,1,1006,1003,1006,1001,1006,2000,1006,\
+             9,1006,1201,0,0,1007,1002,1006,-1,1008,9,1008,\
+             4,1007,1005,1007,82,1105,1,0,\
+             1001,1003,0,1000,1001,1004,0,1001,1105,1,0",
+        );
+        program.resize(1000, 0);
+        program.extend(&[1, 1]);
+        program.resize(2000, 0);
+        for row in rows {
+            program.extend(row.chars().map(|c| match c {
+                '#' => 0,
+                'O' => 2,
+                _ => 1,
+            }));
+        }
+        Machine::new(program)
+    }
+
+    const MAZE: [&str; 5] = [
+        "########", //
+        "#..#..O#", //
+        "#.##.###", //
+        "#......#", //
+        "########",
+    ];
+
+    fn palette(t: i64) -> char {
+        match t {
+            0 => '#',
+            2 => 'O',
+            _ => '.',
+        }
+    }
+
+    #[test]
+    fn test_headings() {
+        assert_eq!(Heading::North.turn_left(), Heading::West);
+        assert_eq!(Heading::North.turn_right(), Heading::East);
+        assert_eq!(Heading::East.reverse(), Heading::West);
+    }
+
+    #[test]
+    fn test_paint() {
+        // Paints white and turns left twice
+        let program = Machine::new(parse("3,100,104,1,104,0,3,100,104,1,104,0,99"));
+        let mut robot = GridRobot::new(program);
+        assert_eq!(robot.paint(), Ok(2));
+        assert_eq!(robot.pos(), (-1, 1));
+        assert_eq!(robot.heading(), Heading::South);
+        assert_eq!(robot.tile((-1, 0)), Some(1));
+    }
+
+    #[test]
+    fn test_depth_first() {
+        let mut robot = GridRobot::new(maze_droid(&MAZE));
+        DepthFirst.explore(&mut robot).unwrap();
+        assert_eq!(robot.pos(), (0, 0));
+        assert_eq!(
+            robot.render(palette),
+            " ## ### \n\
+             #<.#..O#\n\
+             #.##.## \n\
+             #......#\n \
+             ###### \n"
+        );
+        let path = robot.shortest_path((0, 0), (5, 0)).unwrap();
+        assert_eq!(path.len(), 9);
+    }
+
+    #[test]
+    fn test_breadth_first() {
+        let mut robot = GridRobot::new(maze_droid(&MAZE));
+        let mut bfs = BreadthFirst {
+            target: Some(2),
+            ..Default::default()
+        };
+        bfs.explore(&mut robot).unwrap();
+        assert_eq!(bfs.found, Some(((5, 0), 9)));
+        assert_eq!(robot.pos(), (0, 0));
+
+        let path = robot.shortest_path((0, 0), (5, 0)).unwrap();
+        robot.follow(&path).unwrap();
+        assert_eq!(robot.pos(), (5, 0));
+    }
+
+    #[test]
+    fn test_probe() {
+        // Reads x and y, outputs whether x < y
+        let drone = Machine::new(parse("3,100,3,101,7,100,101,102,4,102,99"));
+        assert_eq!(probe(&drone, (1, 2)), Ok(1));
+        assert_eq!(probe(&drone, (2, 1)), Ok(0));
+    }
+}
@@ -0,0 +1,424 @@
+//! Framebuffer adapter for programs that draw with `(x, y, tile)` output triples and read a
+//! joystick position whenever they need input.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+use super::{Machine, Outcome};
+
+/// Sparse 2D tile buffer, coordinates can be negative.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Screen {
+    tiles: HashMap<(i64, i64), i64>,
+}
+
+/// Inclusive bounds `(min, max)` of a screen area.
+pub type Bounds = ((i64, i64), (i64, i64));
+
+impl Screen {
+    pub fn new() -> Screen {
+        Screen::default()
+    }
+
+    pub fn set(&mut self, x: i64, y: i64, tile: i64) {
+        self.tiles.insert((x, y), tile);
+    }
+
+    pub fn get(&self, x: i64, y: i64) -> Option<i64> {
+        self.tiles.get(&(x, y)).copied()
+    }
+
+    pub fn tiles(&self) -> &HashMap<(i64, i64), i64> {
+        &self.tiles
+    }
+
+    /// Positions holding the given tile
+    pub fn find(&self, tile: i64) -> Vec<(i64, i64)> {
+        let mut found: Vec<(i64, i64)> = self
+            .tiles
+            .iter()
+            .filter(|(_, t)| **t == tile)
+            .map(|(p, _)| *p)
+            .collect();
+        found.sort();
+        found
+    }
+
+    pub fn count(&self, tile: i64) -> usize {
+        self.tiles.values().filter(|t| **t == tile).count()
+    }
+
+    pub fn bounds(&self) -> Option<Bounds> {
+        let mut it = self.tiles.keys();
+        let first = *it.next()?;
+        Some(it.fold((first, first), |((x0, y0), (x1, y1)), (x, y)| {
+            ((x0.min(*x), y0.min(*y)), (x1.max(*x), y1.max(*y)))
+        }))
+    }
+
+    /// Renders the screen as text, one line per row, unknown cells are blank.
+    pub fn render<F>(&self, palette: F) -> String
+    where
+        F: Fn(i64) -> char,
+    {
+        let ((x0, y0), (x1, y1)) = match self.bounds() {
+            Some(b) => b,
+            None => return String::new(),
+        };
+        let mut s = String::new();
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                s.push(self.get(x, y).map(&palette).unwrap_or(' '));
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    /// Rasterises the given area, each tile becomes a `scale`×`scale` block of pixels.
+    fn rgb<F>(
+        &self,
+        bounds: Bounds,
+        scale: usize,
+        colour: &F,
+    ) -> Result<(usize, usize, Vec<u8>), &'static str>
+    where
+        F: Fn(i64) -> [u8; 3],
+    {
+        if scale == 0 {
+            return Err("Scale must be at least 1");
+        }
+        let ((x0, y0), (x1, y1)) = bounds;
+        let width = (x1 - x0 + 1) as usize * scale;
+        let height = (y1 - y0 + 1) as usize * scale;
+        let mut pixels = Vec::with_capacity(width * height * 3);
+        for row in 0..height {
+            for col in 0..width {
+                let x = x0 + (col / scale) as i64;
+                let y = y0 + (row / scale) as i64;
+                pixels.extend(self.get(x, y).map(colour).unwrap_or([0, 0, 0]));
+            }
+        }
+        Ok((width, height, pixels))
+    }
+
+    /// Binary PPM (P6) image of the screen, 0×0 when it is empty
+    pub fn to_ppm<F>(&self, scale: usize, colour: F) -> Result<Vec<u8>, &'static str>
+    where
+        F: Fn(i64) -> [u8; 3],
+    {
+        match self.bounds() {
+            Some(bounds) => Ok(encode_ppm(self.rgb(bounds, scale, &colour)?)),
+            None => Ok(encode_ppm((0, 0, vec![]))),
+        }
+    }
+
+    /// PNG image of the screen, PNG has no empty images so the screen must have a tile
+    pub fn to_png<F>(&self, scale: usize, colour: F) -> Result<Vec<u8>, &'static str>
+    where
+        F: Fn(i64) -> [u8; 3],
+    {
+        let bounds = self.bounds().ok_or("Empty screen")?;
+        encode_png(self.rgb(bounds, scale, &colour)?)
+    }
+}
+
+fn encode_ppm((width, height, pixels): (usize, usize, Vec<u8>)) -> Vec<u8> {
+    let mut out = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    out.extend(pixels);
+    out
+}
+
+/// Minimal PNG encoder: 8 bit RGB, no filtering, zlib stream made of stored blocks.
+fn encode_png((width, height, pixels): (usize, usize, Vec<u8>)) -> Result<Vec<u8>, &'static str> {
+    if width == 0 || height == 0 {
+        return Err("Empty image");
+    }
+    let mut raw = Vec::with_capacity(height * (width * 3 + 1));
+    for row in pixels.chunks(width * 3).take(height) {
+        raw.push(0);
+        raw.extend(row);
+    }
+
+    let mut zlib = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = raw.chunks(0xffff).collect();
+    if blocks.is_empty() {
+        zlib.extend(&[1, 0, 0, 0xff, 0xff]);
+    }
+    for (i, block) in blocks.iter().enumerate() {
+        let len = block.len() as u16;
+        zlib.push((i == blocks.len() - 1) as u8);
+        zlib.extend(&len.to_le_bytes());
+        zlib.extend(&(!len).to_le_bytes());
+        zlib.extend(*block);
+    }
+    zlib.extend(&adler32(&raw).to_be_bytes());
+
+    let mut ihdr = vec![];
+    ihdr.extend(&(width as u32).to_be_bytes());
+    ihdr.extend(&(height as u32).to_be_bytes());
+    ihdr.extend(&[8, 2, 0, 0, 0]);
+
+    let mut out = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    png_chunk(&mut out, b"IHDR", &ihdr);
+    png_chunk(&mut out, b"IDAT", &zlib);
+    png_chunk(&mut out, b"IEND", &[]);
+    Ok(out)
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend(kind);
+    out.extend(data);
+    let crc = crc32(&out[start..]);
+    out.extend(&crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// Supplies joystick input whenever the program asks for it.
+pub trait Joystick {
+    fn input(&mut self, screen: &Screen) -> i64;
+}
+
+impl<F> Joystick for F
+where
+    F: FnMut(&Screen) -> i64,
+{
+    fn input(&mut self, screen: &Screen) -> i64 {
+        self(screen)
+    }
+}
+
+/// Image format for recorded frames
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Format {
+    Ppm,
+    Png,
+}
+
+/// Snapshots of the screen, one per joystick read.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Recording {
+    frames: Vec<Screen>,
+}
+
+impl Recording {
+    pub fn new() -> Recording {
+        Recording::default()
+    }
+
+    pub fn frames(&self) -> &[Screen] {
+        &self.frames
+    }
+
+    /// Union of every frame bounds so the animation does not jump around
+    fn bounds(&self) -> Option<Bounds> {
+        self.frames
+            .iter()
+            .filter_map(|f| f.bounds())
+            .fold(None, |acc, ((x0, y0), (x1, y1))| match acc {
+                None => Some(((x0, y0), (x1, y1))),
+                Some(((a0, b0), (a1, b1))) => {
+                    Some(((a0.min(x0), b0.min(y0)), (a1.max(x1), b1.max(y1))))
+                }
+            })
+    }
+
+    /// Writes `<prefix>0000.<ext>`, `<prefix>0001.<ext>`, ... into `dir`.
+    pub fn write_frames<F>(
+        &self,
+        dir: &Path,
+        prefix: &str,
+        format: Format,
+        scale: usize,
+        colour: F,
+    ) -> io::Result<()>
+    where
+        F: Fn(i64) -> [u8; 3],
+    {
+        let bounds = match self.bounds() {
+            Some(b) => b,
+            None => return Ok(()),
+        };
+        let invalid = |e| io::Error::new(io::ErrorKind::InvalidInput, e);
+        for (i, frame) in self.frames.iter().enumerate() {
+            let rgb = frame.rgb(bounds, scale, &colour).map_err(invalid)?;
+            let (bytes, ext) = match format {
+                Format::Ppm => (encode_ppm(rgb), "ppm"),
+                Format::Png => (encode_png(rgb).map_err(invalid)?, "png"),
+            };
+            let mut file = File::create(dir.join(format!("{}{:04}.{}", prefix, i, ext)))?;
+            file.write_all(&bytes)?;
+        }
+        Ok(())
+    }
+}
+
+/// Drives a machine, turning its output triples into a screen.
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    machine: Machine,
+    screen: Screen,
+    pending: Vec<i64>,
+}
+
+impl Framebuffer {
+    pub fn new(machine: Machine) -> Framebuffer {
+        Framebuffer {
+            machine,
+            screen: Screen::new(),
+            pending: vec![],
+        }
+    }
+
+    pub fn screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    /// Runs until the program halts, asking the joystick for input and recording a frame
+    /// each time the program reads it.
+    pub fn run<J>(
+        &mut self,
+        joystick: &mut J,
+        mut recording: Option<&mut Recording>,
+    ) -> Result<(), &'static str>
+    where
+        J: Joystick,
+    {
+        loop {
+            match self.machine.run()? {
+                Outcome::Halted => break,
+                Outcome::BudgetExceeded(_) => return Err("Budget exceeded"),
+                Outcome::NeedsInput => {
+                    if let Some(r) = recording.as_mut() {
+                        r.frames.push(self.screen.clone());
+                    }
+                    let input = joystick.input(&self.screen);
+                    self.machine.push_input(input);
+                }
+                Outcome::Output(v) => {
+                    self.pending.push(v);
+                    if self.pending.len() == 3 {
+                        self.screen
+                            .set(self.pending[0], self.pending[1], self.pending[2]);
+                        self.pending.clear();
+                    }
+                }
+            }
+        }
+        if let Some(r) = recording {
+            r.frames.push(self.screen.clone());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse;
+    use super::*;
+
+    // Draws tile 3 at (1, 2), reads the joystick into cell 100 and draws it at (0, 0)
+    const PROGRAM: &str = "104,1,104,2,104,3,3,100,104,0,104,0,4,100,99";
+
+    #[test]
+    fn test_collects_triples() {
+        let mut fb = Framebuffer::new(Machine::new(parse(PROGRAM)));
+        let mut recording = Recording::new();
+        fb.run(&mut |_: &Screen| 7, Some(&mut recording)).unwrap();
+        assert_eq!(fb.screen().get(1, 2), Some(3));
+        assert_eq!(fb.screen().get(0, 0), Some(7));
+        assert_eq!(fb.screen().bounds(), Some(((0, 0), (1, 2))));
+        assert_eq!(recording.frames().len(), 2);
+        assert_eq!(recording.frames()[0].get(0, 0), None);
+    }
+
+    #[test]
+    fn test_joystick_sees_screen() {
+        let mut fb = Framebuffer::new(Machine::new(parse(PROGRAM)));
+        let mut policy = |s: &Screen| s.count(3) as i64;
+        fb.run(&mut policy, None).unwrap();
+        assert_eq!(fb.screen().find(1), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn test_render() {
+        let mut s = Screen::new();
+        s.set(0, 0, 1);
+        s.set(2, 1, 2);
+        let text = s.render(|t| if t == 1 { '#' } else { 'o' });
+        assert_eq!(text, "#  \n  o\n");
+    }
+
+    #[test]
+    fn test_images() {
+        let mut s = Screen::new();
+        s.set(0, 0, 1);
+        s.set(1, 0, 0);
+        let colour = |t| if t == 1 { [255, 255, 255] } else { [0, 0, 0] };
+        let ppm = s.to_ppm(2, colour).unwrap();
+        assert!(ppm.starts_with(b"P6\n4 2\n255\n"));
+        assert_eq!(ppm.len(), 11 + 4 * 2 * 3);
+
+        let png = s.to_png(2, colour).unwrap();
+        assert_eq!(
+            &png[..8],
+            &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']
+        );
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert!(png.ends_with(&[0xae, 0x42, 0x60, 0x82]));
+    }
+
+    #[test]
+    fn test_empty_images() {
+        let (empty, colour) = (Screen::new(), |_| [255, 255, 255]);
+        assert_eq!(empty.to_ppm(3, colour), Ok(b"P6\n0 0\n255\n".to_vec()));
+        assert_eq!(empty.to_png(3, colour), Err("Empty screen"));
+        assert!(Recording::new()
+            .write_frames(Path::new("/nonexistent"), "f", Format::Png, 3, colour)
+            .is_ok());
+
+        let mut s = Screen::new();
+        s.set(0, 0, 1);
+        assert_eq!(s.to_png(0, colour), Err("Scale must be at least 1"));
+        assert_eq!(s.to_ppm(0, colour), Err("Scale must be at least 1"));
+        let mut recording = Recording::new();
+        recording.frames.push(s);
+        let e = recording
+            .write_frames(Path::new("/nonexistent"), "f", Format::Ppm, 0, colour)
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -1,12 +1,231 @@
+use std::env;
+use std::fs;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Read};
+use std::process;
 
-mod day01;
-mod day02;
-mod day03;
-mod day04;
+use aoc2019::day03::report::{Report, SortBy};
+use aoc2019::intcode::program::Program;
+use aoc2019::{day01, day02, day03, day04};
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("intcode") => intcode(&args[1..]),
+        Some("day03") => wires(&args[1..]),
+        Some("day04") => passwords(&args[1..]),
+        _ => {
+            answers();
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+/// `intcode run [--set CELL=VALUE]... [--input VALUE]... [--dump CELL|all]... FILE`
+///
+/// Runs a program to completion, printing every output then the dumped cells.
+fn intcode(args: &[String]) -> Result<(), String> {
+    let usage =
+        "usage: intcode run [--set CELL=VALUE]... [--input VALUE]... [--dump CELL|all]... FILE";
+    if args.first().map(String::as_str) != Some("run") {
+        return Err(usage.to_string());
+    }
+    let (mut sets, mut inputs, mut dumps, mut file) = (vec![], vec![], vec![], None);
+    let mut it = args[1..].iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--set" | "--input" | "--dump" => {
+                let value = it.next().ok_or_else(|| format!("{} needs a value", arg))?;
+                match arg.as_str() {
+                    "--set" => sets.push(value),
+                    "--input" => inputs.push(
+                        value
+                            .parse()
+                            .map_err(|_| format!("bad input {:?}", value))?,
+                    ),
+                    _ => dumps.push(value),
+                }
+            }
+            _ if file.is_none() && !arg.starts_with("--") => file = Some(arg),
+            _ => return Err(usage.to_string()),
+        }
+    }
+    let file = file.ok_or_else(|| usage.to_string())?;
+    let source = fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+    let program = Program::parse(&source)?;
+    let mut machine = program.machine()?;
+    for set in sets {
+        let (addr, value) = program.parse_patch(set)?;
+        machine.patch(addr, value)?;
+    }
+    for output in machine.run_to_halt(&inputs)? {
+        println!("{}", output);
+    }
+    for cell in dumps {
+        if cell == "all" {
+            let memory: Vec<String> = machine.memory().iter().map(|v| v.to_string()).collect();
+            println!("{}", memory.join(","));
+        } else {
+            let addr = program
+                .address(cell)
+                .ok_or_else(|| format!("unknown cell {:?}", cell))?;
+            println!("[{}] = {}", cell, machine.get(addr));
+        }
+    }
+    Ok(())
+}
+
+/// `day03 render --svg OUT [--size PIXELS] [FILE]`
+/// `day03 report [--sort distance|steps|position] [--format csv|json] [FILE]`
+fn wires(args: &[String]) -> Result<(), String> {
+    match args.first().map(String::as_str) {
+        Some("render") => render(&args[1..]),
+        Some("report") => report(&args[1..]),
+        _ => Err(String::from(
+            "usage: day03 render|report [OPTION]... [FILE], see the subcommands for the options",
+        )),
+    }
+}
+
+/// The wires of `file`, the puzzle input by default
+fn read_wires(file: Option<&str>) -> Result<Vec<day03::Wire>, String> {
+    let file = file.unwrap_or("src/day03/input.txt");
+    let content = fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+    day03::parse_input(content.trim()).map_err(|e| format!("{}: {}", file, e))
+}
+
+/// `day03 render --svg OUT [--size PIXELS] [FILE]`
+///
+/// Draws the wires of `FILE`, the puzzle input by default, to an SVG picture.
+fn render(args: &[String]) -> Result<(), String> {
+    let usage = "usage: day03 render --svg OUT [--size PIXELS] [FILE]";
+    let (mut out, mut size, mut file) = (None, 800, None);
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--svg" | "--size" => {
+                let value = it.next().ok_or_else(|| format!("{} needs a value", arg))?;
+                if arg == "--svg" {
+                    out = Some(value);
+                } else {
+                    size = value.parse().map_err(|_| format!("bad size {:?}", value))?;
+                }
+            }
+            _ if file.is_none() && !arg.starts_with("--") => file = Some(arg.as_str()),
+            _ => return Err(usage.to_string()),
+        }
+    }
+    let out = out.ok_or_else(|| usage.to_string())?;
+    let wires = read_wires(file)?;
+    let svg = day03::svg::render(&wires, size)?;
+    fs::write(out, svg).map_err(|e| format!("{}: {}", out, e))
+}
+
+/// `day03 report [--sort distance|steps|position] [--format csv|json] [FILE]`
+///
+/// Prints every crossing of the wires of `FILE`, the puzzle input by default.
+fn report(args: &[String]) -> Result<(), String> {
+    let usage = "usage: day03 report [--sort distance|steps|position] [--format csv|json] [FILE]";
+    let (mut sort, mut json, mut file) = (SortBy::Position, false, None);
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--sort" | "--format" => {
+                let value = it.next().ok_or_else(|| format!("{} needs a value", arg))?;
+                match (arg.as_str(), value.as_str()) {
+                    ("--sort", _) => {
+                        sort = SortBy::from(value).ok_or_else(|| format!("bad sort {:?}", value))?
+                    }
+                    (_, "csv") => json = false,
+                    (_, "json") => json = true,
+                    _ => return Err(format!("bad format {:?}", value)),
+                }
+            }
+            _ if file.is_none() && !arg.starts_with("--") => file = Some(arg.as_str()),
+            _ => return Err(usage.to_string()),
+        }
+    }
+    let mut report = Report::new(&read_wires(file)?);
+    report.sort_by(sort);
+    print!(
+        "{}",
+        if json {
+            report.to_json()
+        } else {
+            report.to_csv()
+        }
+    );
+    Ok(())
+}
+
+/// `day04 count --rules SPEC|--rules-file FILE [--radix RADIX] [LO-HI]`
+///
+/// Counts the codes from `LO` to `HI` that follow the rules, written in base `RADIX` with as
+/// many digits as `HI`. Both ends are included, as in the puzzle answers, and are written with
+/// digits only. The range is the puzzle input by default.
+fn passwords(args: &[String]) -> Result<(), String> {
+    let usage = "usage: day04 count --rules SPEC|--rules-file FILE [--radix RADIX] [LO-HI]";
+    if args.first().map(String::as_str) != Some("count") {
+        return Err(usage.to_string());
+    }
+    let (mut spec, mut radix, mut range) = (None, 10, None);
+    let mut it = args[1..].iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--rules" | "--rules-file" | "--radix" => {
+                let value = it.next().ok_or_else(|| format!("{} needs a value", arg))?;
+                match arg.as_str() {
+                    "--rules" => spec = Some(value.clone()),
+                    "--rules-file" => {
+                        spec = Some(
+                            fs::read_to_string(value).map_err(|e| format!("{}: {}", value, e))?,
+                        )
+                    }
+                    _ => {
+                        radix = value
+                            .parse()
+                            .map_err(|_| format!("bad radix {:?}", value))?
+                    }
+                }
+            }
+            _ if range.is_none() && !arg.starts_with("--") => range = Some(arg.clone()),
+            _ => return Err(usage.to_string()),
+        }
+    }
+    let spec = spec.ok_or_else(|| usage.to_string())?;
+    if !(2..=36).contains(&radix) {
+        return Err(format!("radix {} is not between 2 and 36", radix));
+    }
+    let rule = day04::spec::parse(&spec)?;
+    let range = match range {
+        Some(range) => range,
+        None => {
+            let file = "src/day04/input.txt";
+            fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?
+        }
+    };
+    let (lo, hi) = range
+        .trim()
+        .split_once('-')
+        .ok_or_else(|| format!("bad range {:?}, expected LO-HI", range.trim()))?;
+    let bound = |s: &str| {
+        let bad = || format!("bad bound {:?} in base {}", s, radix);
+        // `from_str_radix` takes a sign, which would count as a digit of the width
+        if !s.chars().all(|c| c.is_digit(radix)) {
+            return Err(bad());
+        }
+        u64::from_str_radix(s, radix).map_err(|_| bad())
+    };
+    let count = day04::count(bound(lo)?..=bound(hi)?, hi.len(), radix, &rule)?;
+    println!("{}", count);
+    Ok(())
+}
+
+fn answers() {
     let file = File::open("src/day01/input.txt").unwrap();
     let reader = BufReader::new(file);
     let lines = reader.lines().map(|x| x.unwrap().parse().unwrap());